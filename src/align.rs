@@ -0,0 +1,154 @@
+use std::ops::Range;
+
+use fancy_regex::RegexBuilder;
+use fancy_regex_macro::regex;
+
+use crate::{parse::capture_classes, Phonet};
+
+/// Parts of a single word matched by each class, for every positive rule which allows it
+///
+/// Create with `Phonet::align()`
+pub struct Alignment {
+    /// Word that was aligned, without stripped diacritics
+    pub word: String,
+    /// Alignment of each positive rule which matched word, in order of definition
+    pub rules: Vec<RuleAlignment>,
+}
+
+/// Parts of a word matched by each class of a single positive rule
+pub struct RuleAlignment {
+    /// Index of rule
+    pub rule: usize,
+    /// Pattern of rule as written in scheme file, before classes are substituted
+    pub source: String,
+    /// Line of rule in scheme file
+    pub line: usize,
+    /// Byte span of match of rule in word
+    pub span: Range<usize>,
+    /// Part of word matched by each class written in rule, in order of word
+    pub segments: Vec<Segment>,
+}
+
+/// Part of a word matched by a class
+#[derive(Debug, PartialEq, Eq)]
+pub struct Segment {
+    /// Name of class
+    pub class: String,
+    /// Byte span of part of word
+    pub span: Range<usize>,
+}
+
+impl Phonet {
+    /// Find which class matched each part of word, for every positive rule which matches it
+    ///
+    /// Use with words which are valid, but should not be, to find which class allows too much
+    ///
+    /// Only classes written in rule are found, not classes used by those classes.
+    /// A class in a repeated group only has its last match.
+    /// Rules with numbered back-references are skipped, as the groups of classes would change their numbers.
+    /// Rules with custom validators are also skipped
+    ///
+    /// Diacritics set with `%` directives are removed from word first
+    pub fn align(&self, word: &str) -> Alignment {
+        let word = self.strip_diacritics(word);
+
+        let rules = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.intent && rule.validator.is_none())
+            .filter(|(_, rule)| !regex!(r"\\\d").is_match(&rule.template).unwrap_or(true))
+            .filter_map(|(index, rule)| {
+                let (pattern, names) = capture_classes(&rule.template, &self.classes)?;
+
+                let mut builder = RegexBuilder::new(&pattern);
+                if let Some(limit) = rule.backtrack_limit {
+                    builder.backtrack_limit(limit);
+                }
+                let captures = builder.build().ok()?.captures(&word).ok()??;
+                let found = captures.get(0)?;
+
+                let mut segments: Vec<Segment> = names
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(group, class)| {
+                        let found = captures.name(&format!("phonet_class_{group}"))?;
+                        Some(Segment {
+                            class,
+                            span: found.start()..found.end(),
+                        })
+                    })
+                    .collect();
+                segments.sort_by_key(|segment| segment.span.start);
+
+                Some(RuleAlignment {
+                    rule: index,
+                    source: rule.source.clone(),
+                    line: rule.line,
+                    span: found.start()..found.end(),
+                    segments,
+                })
+            })
+            .collect();
+
+        Alignment { word, rules }
+    }
+}
+
+impl Alignment {
+    /// Display alignment to standard output
+    ///
+    /// This can be implemented manually
+    pub fn display(&self, no_color: bool) {
+        if no_color {
+            println!("Aligning '{}':", self.word);
+        } else {
+            println!(
+                "\x1b[34mAligning '\x1b[0;3m{}\x1b[0;34m':\x1b[0m",
+                self.word
+            );
+        }
+
+        if self.rules.is_empty() {
+            if no_color {
+                println!("  No positive rules matched");
+            } else {
+                println!("  \x1b[2mNo positive rules matched\x1b[0m");
+            }
+            return;
+        }
+
+        for rule in &self.rules {
+            let matched = &self.word[rule.span.clone()];
+
+            if no_color {
+                println!(
+                    " #{index} line {line}  + {source}  matched '{matched}'",
+                    index = rule.rule,
+                    line = rule.line,
+                    source = rule.source,
+                );
+            } else {
+                println!(
+                    "  \x1b[2m#{index} line {line}\x1b[0m  \x1b[36m+\x1b[0m {source}  \x1b[2mmatched '\x1b[0;3m{matched}\x1b[0;2m'\x1b[0m",
+                    index = rule.rule,
+                    line = rule.line,
+                    source = rule.source,
+                );
+            }
+
+            for segment in &rule.segments {
+                let text = &self.word[segment.span.clone()];
+
+                if no_color {
+                    println!("      '{text}' <{}>", segment.class);
+                } else {
+                    println!(
+                        "      '\x1b[3m{text}\x1b[0m' \x1b[33m<{}>\x1b[0m",
+                        segment.class
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,13 @@
 // * This file is just for the binary
 
-use clap::{builder::PossibleValue, Parser, ValueEnum};
+use std::str::FromStr;
 
-use phonet::DisplayLevel::{self, *};
+use clap::{builder::PossibleValue, Parser, Subcommand, ValueEnum};
+
+use phonet::{
+    DisplayLevel::{self, *},
+    NoteFilter, ReportFormat,
+};
 
 #[derive(Parser)]
 #[clap(author, version)]
@@ -10,24 +15,112 @@ use phonet::DisplayLevel::{self, *};
 ///
 /// More information: https://github.com/darccyy/phonet
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Define a class after the file, replacing any class with the same name. Can be used multiple times
+    ///
+    /// Use to try changes to a file, without editing it
+    ///
+    /// Eg. `phonet --define '$V = [aeiouy]'`
+    #[arg(long)]
+    pub define: Vec<String>,
+
+    /// Add a rule after the rules of the file. Can be used multiple times
+    ///
+    /// Eg. `phonet --extra-rule '! <V><V> @ No diphthongs'`
+    #[arg(long)]
+    pub extra_rule: Vec<String>,
+
     /// Custom test, separate with comma (Ignores tests in file)
     #[arg(short, long)]
     pub tests: Option<String>,
 
+    /// Validate each line of a file as a word, instead of running tests
+    ///
+    /// Use `-` for standard input. Each result is printed as it is checked, so large files can be used
+    ///
+    /// Eg. `phonet --words dictionary.txt` or `cat words.txt | phonet --words -`
+    #[arg(short, long)]
+    pub words: Option<String>,
+
     /// Name and path of file to run and test
     ///
-    /// Eg. `phonet -f ./myfile.phonet`
-    #[arg(short, long, default_value_t = String::from("phonet"))]
+    /// If a directory is given, every `.phonet` file in it is parsed and run in parallel, and results are displayed as each file finishes
+    /// Options which write files, filter tests, or replace the display (such as `--report`, `-m`, or `-t`) cannot be used with a directory
+    ///
+    /// Eg. `phonet -f ./myfile.phonet` or `phonet -f ./schemes`
+    #[arg(short, long, default_value_t = String::from("phonet"), global = true)]
     pub file: String,
 
     /// What types of outputs to display
     ///
-    /// Options can be single letter
+    /// Options can be single letter, and are not case-sensitive
     ///
-    /// Eg. `phonet -d just-fails` or `phonet -df`
-    #[arg(short, long, default_value_t = ShowAll, value_enum)]
+    /// Eg. `phonet -d just-fails` or `phonet -df` or `phonet -d Fails`
+    #[arg(short, long, default_value_t = ShowAll, value_enum, ignore_case = true)]
     pub display_level: DisplayLevel,
 
+    /// Display which class matched each part of words of `?!` tests which are valid, for every positive rule
+    ///
+    /// Use to find which class allows too much
+    #[arg(long)]
+    pub align: bool,
+
+    /// Which notes to display, if display level includes notes
+    ///
+    /// Eg. `phonet --notes headings`, to only display headings between tests
+    #[arg(long, default_value_t = NoteFilter::All, value_enum, ignore_case = true)]
+    pub notes: NoteFilter,
+
+    /// Display the rule which made each test fail, with its line
+    ///
+    /// Rule is displayed as written, and as a pattern with classes substituted
+    #[arg(long)]
+    pub verbose_failures: bool,
+
+    /// Amount of threads to run tests on
+    ///
+    /// Results are always displayed in order of the file
+    ///
+    /// Use 0 for all available threads
+    #[arg(short, long, default_value_t = 1)]
+    pub jobs: usize,
+
+    /// Mark tests which took at least this many milliseconds to run, with their duration
+    ///
+    /// Eg. `phonet --slow 500` displays `SLOW 1.2s` after a test which took 1.2 seconds
+    #[arg(long)]
+    pub slow: Option<u64>,
+
+    /// List this amount of slowest tests after results, with their duration and line
+    ///
+    /// Use to find words which make a rule slow
+    #[arg(long, default_value_t = 0)]
+    pub slowest: usize,
+
+    /// Validate every test, instead of reusing the result of repeated words
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Print a machine-readable report instead of displaying results
+    ///
+    /// Use `format=path` to save report to a file, and still display results. Can be used multiple times
+    ///
+    /// Formats: `json` (JSON event stream, compatible with `cargo test -- --format json`), `quickfix` (Line of `file:line:col: message` for each failure, for editor quickfix lists), `canonical` (Plain text with a line for each test and note, without colors or times, to commit and diff), `junit` (JUnit XML, with a test suite for each section, for CI dashboards), `csv` (CSV table with a row for each section and each test, for spreadsheets)
+    ///
+    /// Eg. `phonet -r json` or `phonet -r junit=results.xml -r json=results.json`
+    #[arg(short, long)]
+    pub report: Vec<Report>,
+
+    /// Print results as plain text, with a line for each test and note, instead of displaying results
+    ///
+    /// Output has no colors or times, and is in order of the file, so it can be committed and diffed to find changes in results
+    ///
+    /// Same as `--report canonical`. Eg. `phonet --canonical > results.txt`
+    #[arg(long)]
+    pub canonical: bool,
+
     /// Minify file and save
     #[arg(short, long, value_enum)]
     pub minify: Option<Option<WithTests>>,
@@ -52,13 +145,244 @@ pub struct Args {
     #[arg(long = "gmax")]
     pub generate_max_len: Option<usize>,
 
+    /// Generate words with letter frequencies of valid words of `?+` tests
+    ///
+    /// Use with the `--generate` or `-g` flag
+    ///
+    /// Generated words will "sound like" the words of tests
+    #[arg(long)]
+    pub weighted: bool,
+
+    /// Set maximum attempts to generate each word, before giving up
+    ///
+    /// Use with the `--generate` or `-g` flag
+    ///
+    /// Default 100000
+    #[arg(long = "gtries")]
+    pub generate_attempts: Option<usize>,
+
+    /// Save generated words to file, instead of printing
+    ///
+    /// Format is chosen by file extension: `.json` (array of words), `.csv` (words and syllable counts), or any other (one word per line)
+    ///
+    /// Syllables are counted with the `V` class, if defined
+    ///
+    /// Eg. `phonet -g 100 -o words.csv`
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Print a single summary line to standard error
+    ///
+    /// This is printed regardless of display level
+    ///
+    /// Eg. `phonet: 412 passed, 3 failed, 2 notes, 31ms`
+    #[arg(long)]
+    pub summary_line: bool,
+
+    /// Display reasons in another language, from translations file
+    ///
+    /// Translations file is JSON, with translations of each reason for each language
+    ///
+    /// Eg. `phonet --lang es`, with `{ "es": { "No 'x'": "Sin 'x'" } }` in `phonet.lang.json`
+    #[arg(long)]
+    pub lang: Option<String>,
+
+    /// Path of translations file, for `--lang`
+    ///
+    /// Default is name of file, with `.lang.json` after it
+    #[arg(long)]
+    pub translations: Option<String>,
+
+    /// Save counts of passed and failed tests, and reasons of failed tests, to end of history file
+    ///
+    /// Default path is `.<name>.phonet-history.json`, next to the file. Use `phonet trend` to display pass rate over time
+    ///
+    /// Eg. `phonet --history` or `phonet --history history.json`
+    #[arg(long)]
+    pub history: Option<Option<String>>,
+
+    /// Only run tests which failed in the last run, until they all pass
+    ///
+    /// Failed tests are saved to `.<name>.phonet-state.json`, next to the file
+    #[arg(long)]
+    pub rerun_failed: bool,
+
+    /// Only run a random subset of tests, with this amount
+    ///
+    /// Notes are not displayed. Use for quick checks of files with many tests
+    ///
+    /// Eg. `phonet --sample 500`
+    #[arg(long)]
+    pub sample: Option<usize>,
+
+    /// Seed for random sampling of tests, to choose the same tests again
+    ///
+    /// Use with the `--sample` flag. The seed is printed if not given
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Run tests and notes in a random order
+    ///
+    /// The seed is printed, to run in the same order again. Specify with number
+    ///
+    /// Eg. `phonet --shuffle` or `phonet --shuffle 42`
+    #[arg(long)]
+    pub shuffle: Option<Option<u64>>,
+
+    /// Compile the regex of each rule only when it is first used
+    ///
+    /// Use for faster startup with large files, when only some rules are needed
+    ///
+    /// Note: Invalid rules are not found until used
+    #[arg(long)]
+    pub lazy: bool,
+
+    /// Maximum amount of backtracking steps for each match of a rule
+    ///
+    /// A test fails if a rule exceeds the limit, instead of running forever. Default 1000000
+    #[arg(long)]
+    pub backtrack_limit: Option<usize>,
+
+    /// Remove tests with the same intent and word as an earlier test
+    ///
+    /// Duplicate tests are not warned about
+    #[arg(long)]
+    pub dedup: bool,
+
+    /// Treat warnings as errors
+    ///
+    /// Eg. A class that is defined twice
+    #[arg(long)]
+    pub deny_warnings: bool,
+
     /// Display output in default color
     ///
     /// Use for piping standard output to a file
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub no_color: bool,
 }
 
+#[derive(Subcommand)]
+pub enum Command {
+    /// Check a single word against every rule, and display a full breakdown
+    ///
+    /// Eg. `phonet explain taso`
+    Explain {
+        /// Word to check
+        word: String,
+    },
+
+    /// Check file for problems which do not stop parsing, without running tests
+    ///
+    /// Eg. Duplicate tests, or rules which never change a result
+    ///
+    /// Also displays how many rules and test segments use each class, highlighting classes which never influence a result
+    ///
+    /// Exits with an error code if any warnings are found
+    Lint,
+
+    /// List sequences of letters which are allowed by the rules, but not found in any word of a corpus
+    ///
+    /// These are accidental gaps, which can be used to coin new words
+    ///
+    /// Eg. `phonet gaps words.txt --length 3`
+    Gaps {
+        /// File of words, one per line. Uses words of valid `?+` tests if not given
+        corpus: Option<String>,
+
+        /// Amount of letters in each sequence
+        #[arg(short, long, default_value_t = 2)]
+        length: usize,
+    },
+
+    /// Validate words with two files, and list words which are valid with one file, but not the other
+    ///
+    /// Use to check that changes to a file do not change any results
+    ///
+    /// Exits with an error code if any words differ
+    ///
+    /// Eg. `phonet compare old.phonet new.phonet --words dictionary.txt`
+    Compare {
+        /// Name and path of first file
+        first: String,
+
+        /// Name and path of second file
+        second: String,
+
+        /// File of words to validate, one per line. Uses words of tests of both files if not given
+        ///
+        /// Use `-` for standard input
+        #[arg(short, long)]
+        words: Option<String>,
+    },
+
+    /// Check that file is consistent, and display a single pass or fail
+    ///
+    /// Checks that every class and rule compiles, every reason is used by a rule, and a valid word can be generated (if the 'any' class is defined)
+    ///
+    /// Exits with an error code if any check fails
+    Selftest,
+
+    /// Display pass rate of each run saved with `--history`, to track changes over time
+    ///
+    /// Eg. `phonet trend` or `phonet trend --history history.json --last 50`
+    Trend {
+        /// Path of history file. Default is `.<name>.phonet-history.json`, next to the file
+        #[arg(long)]
+        history: Option<String>,
+
+        /// Amount of latest runs to display
+        #[arg(long, default_value_t = 20)]
+        last: usize,
+    },
+
+    /// Create file with an example scheme, to start from
+    ///
+    /// Eg. `phonet init -f myfile.phonet`
+    Init {
+        /// Replace file, if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Time parsing, regex compilation, and validation of each word, with each regex backend
+    ///
+    /// Rules which are not supported by `regex` (such as look-arounds) are left out when comparing validation
+    ///
+    /// Eg. `phonet bench --rounds 1000`
+    Bench {
+        /// Amount of times to repeat each stage
+        #[arg(long, default_value_t = 100)]
+        rounds: usize,
+    },
+}
+
+/// Format of report, and file to save to, if any
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub format: ReportFormat,
+    /// Print to standard output if `None`
+    pub path: Option<String>,
+}
+
+// Parse from `format` or `format=path`
+impl FromStr for Report {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (format, path) = match s.split_once('=') {
+            Some((format, path)) if !path.is_empty() => (format, Some(path.to_string())),
+            Some(_) => return Err(format!("Missing path of report file, in '{s}'")),
+            None => (s, None),
+        };
+
+        Ok(Self {
+            format: <ReportFormat as ValueEnum>::from_str(format.trim(), true)?,
+            path,
+        })
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 /// Custom implementation of boolean, for argument aliases
 pub enum WithTests {
@@ -79,3 +403,22 @@ impl ValueEnum for WithTests {
         &[Self::Tests]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_report_works() {
+        let report: Report = "json".parse().unwrap();
+        assert!(matches!(report.format, ReportFormat::Json));
+        assert_eq!(report.path, None);
+
+        let report: Report = "qf=out/phonet.qf".parse().unwrap();
+        assert!(matches!(report.format, ReportFormat::Quickfix));
+        assert_eq!(report.path.as_deref(), Some("out/phonet.qf"));
+
+        assert!("json=".parse::<Report>().is_err());
+        assert!("xml=out.xml".parse::<Report>().is_err());
+    }
+}
@@ -0,0 +1,261 @@
+// * This file is just for the binary
+
+use std::{
+    cmp::Reverse,
+    time::{Duration, Instant},
+};
+
+use phonet::{ParseOptions, Phonet, TestDefinition};
+
+/// Amount of slowest rules to display
+const SLOWEST_RULES: usize = 5;
+
+/// Compiled rule, with each regex backend
+struct BenchRule {
+    intent: bool,
+    fancy: fancy_regex::Regex,
+    /// `None` if pattern uses syntax which is not supported by `regex` (look-arounds, back-references)
+    plain: Option<regex::Regex>,
+}
+
+/// Time parsing, compiling, and validating of scheme, with `fancy-regex` and `regex` backends
+///
+/// Each stage is repeated `rounds` times, and the average is displayed
+pub fn bench(file: &str, rounds: usize, no_color: bool) -> Result<(), phonet::Error> {
+    let rounds = rounds.max(1);
+
+    // Parse, without compiling
+    let start = Instant::now();
+    let options = ParseOptions {
+        lazy: true,
+        ..Default::default()
+    };
+    for _ in 0..rounds {
+        Phonet::parse_with(file, &options)?;
+    }
+    let parse_time = start.elapsed() / rounds as u32;
+
+    let scheme = Phonet::parse_with(file, &options)?;
+
+    // Compile with both backends
+    let mut fancy_compile = Duration::ZERO;
+    let mut plain_compile = Duration::ZERO;
+    let mut rules = Vec::new();
+
+    for rule in &scheme.rules {
+        let start = Instant::now();
+        let fancy =
+            fancy_regex::Regex::new(&rule.pattern).map_err(|err| phonet::Error::RegexFail {
+                rule: rule.source.clone(),
+                err,
+                line: rule.line,
+            })?;
+        fancy_compile += start.elapsed();
+
+        let start = Instant::now();
+        let plain = regex::Regex::new(&rule.pattern).ok();
+        plain_compile += start.elapsed();
+
+        rules.push(BenchRule {
+            intent: rule.intent,
+            fancy,
+            plain,
+        });
+    }
+
+    // Rules which compile with both backends, to compare validation times
+    let supported: Vec<(bool, &fancy_regex::Regex, &regex::Regex)> = rules
+        .iter()
+        .filter_map(|rule| Some((rule.intent, &rule.fancy, rule.plain.as_ref()?)))
+        .collect();
+    let unsupported = rules.len() - supported.len();
+
+    // Words of tests
+    let words: Vec<&str> = scheme
+        .tests
+        .iter()
+        .filter_map(|test| match test {
+            TestDefinition::Test { word, .. } => Some(word.as_str()),
+            TestDefinition::Note(_) => None,
+        })
+        .collect();
+
+    // Validate with both backends
+    let mut fancy_validate = Duration::ZERO;
+    let mut fancy_supported = Duration::ZERO;
+    let mut plain_supported = Duration::ZERO;
+    let mut rule_times = vec![Duration::ZERO; rules.len()];
+
+    for _ in 0..rounds {
+        for word in &words {
+            // `fancy-regex`, timing each rule
+            for (index, rule) in rules.iter().enumerate() {
+                let start = Instant::now();
+                let is_match = rule.fancy.is_match(word).unwrap_or(false);
+                let elapsed = start.elapsed();
+
+                fancy_validate += elapsed;
+                rule_times[index] += elapsed;
+
+                if rule.intent ^ is_match {
+                    break;
+                }
+            }
+
+            // Only supported rules, with each backend
+            // Unsupported rules are left out of both, so only regex engines are compared
+            let start = Instant::now();
+            for (intent, fancy, _) in &supported {
+                if intent ^ fancy.is_match(word).unwrap_or(false) {
+                    break;
+                }
+            }
+            fancy_supported += start.elapsed();
+
+            let start = Instant::now();
+            for (intent, _, plain) in &supported {
+                if intent ^ plain.is_match(word) {
+                    break;
+                }
+            }
+            plain_supported += start.elapsed();
+        }
+    }
+
+    // Average per word
+    let checks = (rounds * words.len()).max(1) as u32;
+    let fancy_validate = fancy_validate / checks;
+    let fancy_supported = fancy_supported / checks;
+    let plain_supported = plain_supported / checks;
+
+    // Display table
+    if no_color {
+        println!(
+            "Benchmark: {} rules, {} tests, {rounds} rounds",
+            rules.len(),
+            words.len()
+        );
+    } else {
+        println!(
+            "\x1b[34mBenchmark: \x1b[0;1m{}\x1b[0;34m rules, \x1b[0;1m{}\x1b[0;34m tests, \x1b[0;1m{rounds}\x1b[0;34m rounds\x1b[0m",
+            rules.len(),
+            words.len()
+        );
+    }
+
+    let mut rows = vec![
+        ("parse", format_duration(parse_time), "-".to_string()),
+        (
+            "compile",
+            format_duration(fancy_compile),
+            format_duration(plain_compile),
+        ),
+    ];
+
+    // Validation of every rule is only compared if `regex` supports every rule
+    if unsupported == 0 {
+        rows.push((
+            "validate (per word)",
+            format_duration(fancy_validate),
+            format_duration(plain_supported),
+        ));
+    } else {
+        rows.push((
+            "validate (per word)",
+            format_duration(fancy_validate),
+            "-".to_string(),
+        ));
+        rows.push((
+            "validate supported",
+            format_duration(fancy_supported),
+            format_duration(plain_supported),
+        ));
+    }
+
+    print_row(("stage", "fancy-regex", "regex"), !no_color);
+    for (stage, fancy, plain) in &rows {
+        print_row((stage, fancy.as_str(), plain.as_str()), false);
+    }
+
+    if unsupported > 0 {
+        if no_color {
+            println!("{unsupported} rules are not supported by regex, and are left out of `validate supported`");
+        } else {
+            println!(
+                "\x1b[33m{unsupported} rules are not supported by regex, and are left out of `validate supported`\x1b[0m"
+            );
+        }
+    }
+
+    // Slowest rules, with fancy-regex
+    let mut slowest: Vec<(usize, Duration)> = rule_times.into_iter().enumerate().collect();
+    slowest.sort_by_key(|(_, time)| Reverse(*time));
+
+    if !words.is_empty() && !slowest.is_empty() {
+        if no_color {
+            println!("Slowest rules:");
+        } else {
+            println!("\x1b[34mSlowest rules:\x1b[0m");
+        }
+
+        for (index, time) in slowest.into_iter().take(SLOWEST_RULES) {
+            let rule = &scheme.rules[index];
+            let intent = if rule.intent { '+' } else { '!' };
+
+            if no_color {
+                println!(
+                    " #{index} {:>10}  {intent} {}",
+                    format_duration(time / rounds as u32),
+                    rule.pattern
+                );
+            } else {
+                println!(
+                    "  \x1b[2m#{index}\x1b[0m {:>10}  \x1b[33m{intent} {}\x1b[0m",
+                    format_duration(time / rounds as u32),
+                    rule.pattern
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Print row of table, with fixed column widths
+fn print_row((stage, fancy, plain): (&str, &str, &str), bold: bool) {
+    let row = format!("{stage:<20} {fancy:>12} {plain:>12}");
+
+    if bold {
+        println!("\x1b[1m{row}\x1b[0m");
+    } else {
+        println!("{row}");
+    }
+}
+
+/// Format duration with a suitable unit
+fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+
+    if nanos < 1_000 {
+        format!("{nanos}ns")
+    } else if nanos < 1_000_000 {
+        format!("{:.1}µs", nanos as f64 / 1e3)
+    } else if nanos < 1_000_000_000 {
+        format!("{:.1}ms", nanos as f64 / 1e6)
+    } else {
+        format!("{:.2}s", nanos as f64 / 1e9)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_duration_works() {
+        assert_eq!(format_duration(Duration::from_nanos(420)), "420ns");
+        assert_eq!(format_duration(Duration::from_nanos(12_340)), "12.3µs");
+        assert_eq!(format_duration(Duration::from_micros(4_560)), "4.6ms");
+        assert_eq!(format_duration(Duration::from_millis(2_500)), "2.50s");
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+
+use crate::{run::validate_test, types::TestDefinition, Phonet};
+
+/// Word which is valid with one scheme, but not the other
+///
+/// Create with `Phonet::compare()`
+#[derive(Debug, PartialEq, Eq)]
+pub struct Difference {
+    /// Word which was checked
+    pub word: String,
+    /// Whether word is valid with first scheme
+    pub first: bool,
+    /// Whether word is valid with second scheme
+    pub second: bool,
+}
+
+impl Phonet {
+    /// Validate each word with both schemes, and return words with different validity
+    ///
+    /// Differences are in order of words given
+    pub fn compare<'a>(
+        &self,
+        other: &Phonet,
+        words: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<Difference> {
+        words
+            .into_iter()
+            .filter_map(|word| {
+                let first = self.is_valid(word);
+                let second = other.is_valid(word);
+
+                (first != second).then(|| Difference {
+                    word: word.to_string(),
+                    first,
+                    second,
+                })
+            })
+            .collect()
+    }
+
+    /// Get words of every test, without duplicates, in order of definition
+    pub fn test_words(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+
+        self.tests
+            .iter()
+            .filter_map(|test| match test {
+                TestDefinition::Test { word, .. } => Some(word.as_str()),
+                TestDefinition::Note(_) => None,
+            })
+            .filter(|word| seen.insert(*word))
+            .collect()
+    }
+
+    /// Returns `true` if word fits every rule
+    ///
+    /// Diacritics set with `%` directives are removed first
+    fn is_valid(&self, word: &str) -> bool {
+        validate_test(&self.strip_diacritics(word), &self.rules).is_valid()
+    }
+}
@@ -0,0 +1,245 @@
+use std::ops::Range;
+
+use crate::{run::validate_test, Phonet, ValidStatus};
+
+/// Breakdown of a single word, checked against every rule
+///
+/// Create with `Phonet::explain()`
+pub struct Explanation {
+    /// Word that was checked, without stripped diacritics
+    pub word: String,
+    /// Result of each rule, in order of definition
+    pub traces: Vec<RuleTrace>,
+}
+
+/// Diagnostics of a single word, with every rule which does not allow it
+///
+/// Create with `Phonet::validate()`
+pub struct WordAnalysis {
+    /// Word that was checked, without stripped diacritics
+    pub word: String,
+    /// Validity of word, from first rule which does not allow it
+    pub validity: ValidStatus,
+    /// Result of each rule which does not allow word, in order of definition
+    pub violations: Vec<RuleTrace>,
+}
+
+/// Result of a single rule, checked against a word
+pub struct RuleTrace {
+    /// Index of rule
+    pub rule: usize,
+    /// Intent of rule
+    pub intent: bool,
+    /// Pattern of rule, with classes substituted
+    pub pattern: String,
+    /// Pattern of rule as written in scheme file, before classes are substituted
+    pub source: String,
+    /// Line of rule in scheme file
+    pub line: usize,
+    /// Byte span of first match in word, if rule matched
+    pub span: Option<Range<usize>>,
+    /// Whether rule allows word
+    pub pass: bool,
+    /// Reason of rule, if given
+    pub reason: Option<String>,
+    /// Message of regex error, if rule could not be compiled or matched
+    pub error: Option<String>,
+}
+
+impl Phonet {
+    /// Check word against every rule, without stopping at first failure
+    ///
+    /// Diacritics set with `%` directives are removed from word first
+    pub fn explain(&self, word: &str) -> Explanation {
+        let word = &self.strip_diacritics(word);
+
+        let traces = self
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                let (span, error) = match &rule.validator {
+                    // Custom validator matches the whole word
+                    Some(validator) => match validator.is_match(word) {
+                        Some(found) => (found.then_some(0..word.len()), None),
+                        None => (None, Some(validator.unregistered_message())),
+                    },
+
+                    None => match rule.regex() {
+                        Ok(pattern) => match pattern.find(word) {
+                            Ok(found) => (found.map(|found| found.start()..found.end()), None),
+                            Err(err) => (None, Some(err.to_string())),
+                        },
+                        Err(err) => (None, Some(err.to_string())),
+                    },
+                };
+
+                RuleTrace {
+                    rule: index,
+                    intent: rule.intent,
+                    pattern: rule.pattern.clone(),
+                    source: rule.source.clone(),
+                    line: rule.line,
+                    // Rule never allows word, if it could not be matched
+                    pass: error.is_none() && rule.intent == span.is_some(),
+                    span,
+                    error,
+                    reason: rule
+                        .reason_ref
+                        .and_then(|reason| self.reasons.get(reason))
+                        .cloned(),
+                }
+            })
+            .collect();
+
+        Explanation {
+            word: word.to_string(),
+            traces,
+        }
+    }
+
+    /// Check word against every rule, and keep every rule which does not allow it
+    ///
+    /// Diacritics set with `%` directives are removed from word first
+    pub fn validate(&self, word: &str) -> WordAnalysis {
+        let explanation = self.explain(word);
+
+        WordAnalysis {
+            validity: validate_test(&explanation.word, &self.rules),
+            word: explanation.word,
+            violations: explanation
+                .traces
+                .into_iter()
+                .filter(|trace| !trace.pass)
+                .collect(),
+        }
+    }
+}
+
+impl WordAnalysis {
+    /// Returns `true` if every rule allows word
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Get first rule which does not allow word, if any
+    pub fn first_violation(&self) -> Option<&RuleTrace> {
+        self.violations.first()
+    }
+
+    /// Get byte spans of matches of negative rules which do not allow word
+    ///
+    /// Positive rules which do not allow word have no match
+    pub fn spans(&self) -> Vec<Range<usize>> {
+        self.violations
+            .iter()
+            .filter_map(|trace| trace.span.clone())
+            .collect()
+    }
+}
+
+impl Explanation {
+    /// Returns `true` if every rule allows word
+    pub fn is_valid(&self) -> bool {
+        self.traces.iter().all(|trace| trace.pass)
+    }
+
+    /// Get first rule which does not allow word, if any
+    pub fn first_failure(&self) -> Option<&RuleTrace> {
+        self.traces.iter().find(|trace| !trace.pass)
+    }
+
+    /// Display breakdown to standard output
+    ///
+    /// This can be implemented manually
+    pub fn display(&self, no_color: bool) {
+        if no_color {
+            println!("Explaining '{}':", self.word);
+        } else {
+            println!(
+                "\x1b[34mExplaining '\x1b[0;3m{}\x1b[0;34m':\x1b[0m",
+                self.word
+            );
+        }
+
+        // Get maximum length of all rules, as written, and of all line numbers
+        let max_source_len = self
+            .traces
+            .iter()
+            .map(|trace| trace.source.chars().count())
+            .max()
+            .unwrap_or(0);
+        let max_line_len = self
+            .traces
+            .iter()
+            .map(|trace| trace.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+
+        for trace in &self.traces {
+            let intent = if trace.intent { '+' } else { '!' };
+            let space = " ".repeat(max_source_len - trace.source.chars().count());
+
+            // Format match
+            let matched = match (&trace.span, &trace.error) {
+                (_, Some(err)) => format!("error: {err}"),
+                (Some(span), _) => format!(
+                    "matched {}..{} '{}'",
+                    span.start,
+                    span.end,
+                    &self.word[span.clone()]
+                ),
+                (None, _) => "no match".to_string(),
+            };
+
+            let reason = match &trace.reason {
+                Some(reason) if !trace.pass => reason.as_str(),
+                _ => "",
+            };
+
+            if no_color {
+                println!(
+                    " #{index} line {line:>max_line_len$}  {intent} {source}{space}  {result}  {matched}  {reason}",
+                    index = trace.rule,
+                    line = trace.line,
+                    source = trace.source,
+                    result = if trace.pass { "pass" } else { "FAIL" },
+                );
+            } else {
+                println!(
+                    "  \x1b[2m#{index} line {line:>max_line_len$}\x1b[0m  \x1b[{intent_color}{intent}\x1b[0m {source}{space}  \x1b[1;{result}\x1b[0m  \x1b[2m{matched}\x1b[0m  \x1b[3;1m{reason}\x1b[0m",
+                    index = trace.rule,
+                    line = trace.line,
+                    intent_color = if trace.intent { "36m" } else { "35m" },
+                    source = trace.source,
+                    result = if trace.pass { "32mpass" } else { "31mFAIL" },
+                );
+            }
+        }
+
+        // Final print
+        match self.first_failure() {
+            None => {
+                if no_color {
+                    println!("Word is valid!");
+                } else {
+                    println!("\x1b[32;1;3mWord is valid!\x1b[0m");
+                }
+            }
+
+            Some(trace) => {
+                if no_color {
+                    println!(
+                        "Word is invalid, first by rule #{}, at line {}",
+                        trace.rule, trace.line
+                    );
+                } else {
+                    println!(
+                        "\x1b[31;1;3mWord is invalid, first by rule #{}, at line {}\x1b[0m",
+                        trace.rule, trace.line
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// * This file is just for the binary
+
+use std::path::Path;
+
+use fancy_regex::Regex;
+use phonet::Phonet;
+use serde_json::json;
+
+/// Format of exported words, inferred from file extension
+#[derive(Debug, PartialEq)]
+pub enum ExportFormat {
+    /// One word per line
+    Text,
+    /// Array of words
+    Json,
+    /// Word and syllable count, per row
+    Csv,
+}
+
+impl ExportFormat {
+    /// Get format from file extension, defaulting to text
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .as_deref()
+        {
+            Some("json") => Self::Json,
+            Some("csv") => Self::Csv,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// Format words to export as string
+pub fn export_words(scheme: &Phonet, words: &[String], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Text => words.iter().map(|word| word.to_string() + "\n").collect(),
+
+        ExportFormat::Json => json!(words).to_string() + "\n",
+
+        ExportFormat::Csv => {
+            let syllables = syllable_regex(scheme);
+
+            let mut output = String::from("word,syllables\n");
+            for word in words {
+                output.push_str(&format!(
+                    "{},{}\n",
+                    csv_field(word),
+                    count_syllables(word, &syllables)
+                ));
+            }
+            output
+        }
+    }
+}
+
+/// Create regex which matches each syllable nucleus
+///
+/// Uses the vowel class (`$V`) if defined, otherwise common vowel letters
+fn syllable_regex(scheme: &Phonet) -> Regex {
+    let vowels = scheme
+        .classes
+        .get("V")
+        .map(String::as_str)
+        .unwrap_or("[aeiouy]");
+
+    Regex::new(&format!("(?:{vowels})+"))
+        .or_else(|_| Regex::new("[aeiouy]+"))
+        .expect("Failed to create syllable regex. This error should NEVER APPEAR!")
+}
+
+/// Count syllable nuclei in word
+fn count_syllables(word: &str, syllables: &Regex) -> usize {
+    syllables
+        .find_iter(word)
+        .filter(|found| found.is_ok())
+        .count()
+}
+
+/// Quote field of CSV row, if needed
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_format_from_path() {
+        assert_eq!(ExportFormat::from_path("words.json"), ExportFormat::Json);
+        assert_eq!(ExportFormat::from_path("out/words.CSV"), ExportFormat::Csv);
+        assert_eq!(ExportFormat::from_path("words.txt"), ExportFormat::Text);
+        assert_eq!(ExportFormat::from_path("words"), ExportFormat::Text);
+    }
+
+    #[test]
+    fn export_words_works() {
+        let scheme = Phonet::parse("$V = [ao]; $C = [tk]").expect("Failed to parse");
+        let words = vec!["taka".to_string(), "kaot".to_string()];
+
+        assert_eq!(
+            export_words(&scheme, &words, ExportFormat::Text),
+            "taka\nkaot\n"
+        );
+        assert_eq!(
+            export_words(&scheme, &words, ExportFormat::Json),
+            "[\"taka\",\"kaot\"]\n"
+        );
+        assert_eq!(
+            export_words(&scheme, &words, ExportFormat::Csv),
+            "word,syllables\ntaka,2\nkaot,1\n"
+        );
+    }
+
+    #[test]
+    fn csv_field_works() {
+        assert_eq!(csv_field("taso"), "taso");
+        assert_eq!(csv_field("ta,so"), "\"ta,so\"");
+        assert_eq!(csv_field("ta\"so"), "\"ta\"\"so\"");
+    }
+}
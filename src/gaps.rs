@@ -0,0 +1,126 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::{run::validate_test, types::Rule, Phonet};
+
+/// Sequence which is possible with the rules, but not found in corpus (accidental gap)
+///
+/// Create with `Phonet::gaps()`
+#[derive(Debug, PartialEq, Eq)]
+pub struct Gap {
+    /// Sequence of letters
+    pub sequence: String,
+    /// Word from corpus, with sequence substituted in, which fits the rules
+    pub example: String,
+}
+
+impl Phonet {
+    /// Find sequences of letters which are not found in any word of corpus, but are allowed by the rules
+    ///
+    /// Each sequence is made of letters found in corpus. A sequence is possible if it can be substituted into some word of corpus, and the result is valid
+    ///
+    /// Invalid words of corpus are ignored, and diacritics set with `%` directives are removed
+    ///
+    /// Note: Amount of sequences checked grows exponentially with `length`
+    pub fn gaps(&self, corpus: &[String], length: usize) -> Vec<Gap> {
+        let words: Vec<Vec<char>> = corpus
+            .iter()
+            .map(|word| self.strip_diacritics(word))
+            .filter(|word| validate_test(word, &self.rules).is_valid())
+            .map(|word| word.chars().collect())
+            .collect();
+
+        if length == 0 {
+            return Vec::new();
+        }
+
+        // Letters and sequences found in corpus
+        let letters: Vec<char> = words
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let attested: HashSet<&[char]> =
+            words.iter().flat_map(|word| word.windows(length)).collect();
+
+        let mut gaps = Vec::new();
+
+        for sequence in sequences(&letters, length) {
+            if attested.contains(sequence.as_slice()) {
+                continue;
+            }
+
+            if let Some(example) = find_example(&words, &sequence, &self.rules) {
+                gaps.push(Gap {
+                    sequence: sequence.iter().collect(),
+                    example,
+                });
+            }
+        }
+
+        gaps
+    }
+}
+
+/// Find word of corpus which is valid, with part of it replaced by sequence
+fn find_example(words: &[Vec<char>], sequence: &[char], rules: &[Rule]) -> Option<String> {
+    for word in words {
+        if word.len() < sequence.len() {
+            continue;
+        }
+
+        for start in 0..=word.len() - sequence.len() {
+            let example: String = word[..start]
+                .iter()
+                .chain(sequence)
+                .chain(&word[start + sequence.len()..])
+                .collect();
+
+            if validate_test(&example, rules).is_valid() {
+                return Some(example);
+            }
+        }
+    }
+    None
+}
+
+/// Get every sequence of letters with length, in order
+fn sequences(letters: &[char], length: usize) -> Vec<Vec<char>> {
+    let mut sequences = vec![Vec::new()];
+
+    for _ in 0..length {
+        sequences = sequences
+            .into_iter()
+            .flat_map(|sequence| {
+                letters.iter().map(move |letter| {
+                    let mut sequence = sequence.clone();
+                    sequence.push(*letter);
+                    sequence
+                })
+            })
+            .collect();
+    }
+
+    sequences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequences_works() {
+        assert_eq!(
+            sequences(&['a', 'b'], 2),
+            vec![
+                vec!['a', 'a'],
+                vec!['a', 'b'],
+                vec!['b', 'a'],
+                vec!['b', 'b'],
+            ]
+        );
+        assert_eq!(sequences(&['a', 'b'], 1).len(), 2);
+        assert!(sequences(&[], 2).is_empty());
+    }
+}
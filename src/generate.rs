@@ -1,27 +1,129 @@
-use std::ops::Range;
+use std::{cmp::Reverse, collections::HashMap, ops::Range};
 
 use rand::{seq::SliceRandom, Rng};
 
-use crate::{types::Error, run::validate_test, Phonet};
+use crate::{
+    run::{validate_test, ValidStatus},
+    types::{Error, TestDefinition},
+    Phonet,
+};
+
+/// Default maximum attempts to generate each word, before giving up
+pub const DEFAULT_GENERATE_ATTEMPTS: usize = 100_000;
+
+/// Maximum amount of rules to list, if generation fails
+const MAX_REJECTION_RULES: usize = 5;
 
 impl Phonet {
     /// Generate random words that fit the rules
+    ///
+    /// Gives up after `DEFAULT_GENERATE_ATTEMPTS` attempts for any word
     pub fn generate(&self, count: usize, length: Range<usize>) -> Result<Vec<String>, Error> {
-        //TODO Handle
-        let letters = match self.classes.get("_") {
-            Some(x) => x,
-            None => return Err(Error::MissingAnyClass),
-        };
+        self.generate_with_attempts(count, length, DEFAULT_GENERATE_ATTEMPTS)
+    }
+
+    /// Generate random words that fit the rules, with maximum attempts for each word
+    ///
+    /// If no valid word is found within `max_attempts`, error lists the rules which rejected the most words
+    pub fn generate_with_attempts(
+        &self,
+        count: usize,
+        length: Range<usize>,
+        max_attempts: usize,
+    ) -> Result<Vec<String>, Error> {
+        let letters = self.any_letters()?;
+
+        self.generate_from(count, length, max_attempts, |length| {
+            random_word(&letters, length)
+        })
+    }
+
+    /// Generate random words that fit the rules, with letter frequencies of words of positive tests
+    ///
+    /// Each letter is chosen by how often it follows the previous letter, in words of valid `?+` tests
+    ///
+    /// Gives up after `DEFAULT_GENERATE_ATTEMPTS` attempts for any word
+    pub fn generate_weighted(
+        &self,
+        count: usize,
+        length: Range<usize>,
+    ) -> Result<Vec<String>, Error> {
+        self.generate_weighted_with_attempts(count, length, DEFAULT_GENERATE_ATTEMPTS)
+    }
+
+    /// Generate random words that fit the rules, with letter frequencies of words of positive tests, with maximum attempts for each word
+    pub fn generate_weighted_with_attempts(
+        &self,
+        count: usize,
+        length: Range<usize>,
+        max_attempts: usize,
+    ) -> Result<Vec<String>, Error> {
+        let corpus = self.corpus();
+        let bigrams = Bigrams::new(corpus.iter().map(String::as_str));
+        if bigrams.is_empty() {
+            return Err(Error::EmptyCorpus);
+        }
+
+        self.generate_from(count, length, max_attempts, |length| {
+            bigrams.random_word(length)
+        })
+    }
+
+    /// Get words of positive tests which are valid, without stripped diacritics
+    pub fn corpus(&self) -> Vec<String> {
+        self.tests
+            .iter()
+            .filter_map(|test| match test {
+                TestDefinition::Test {
+                    intent: true, word, ..
+                } => Some(self.strip_diacritics(word)),
+                _ => None,
+            })
+            .filter(|word| validate_test(word, &self.rules).is_valid())
+            .collect()
+    }
+
+    /// Generate words with function, until each word fits the rules
+    fn generate_from(
+        &self,
+        count: usize,
+        length: Range<usize>,
+        max_attempts: usize,
+        mut make_word: impl FnMut(usize) -> String,
+    ) -> Result<Vec<String>, Error> {
+        if length.is_empty() {
+            return Err(Error::EmptyGenerateLength {
+                min: length.start,
+                max: length.end,
+            });
+        }
 
         let mut words = Vec::new();
         let mut rng = rand::thread_rng();
 
+        // Count of words rejected by each rule
+        let mut rejections = vec![0; self.rules.len()];
+
         for _ in 0..count {
+            let mut attempts = 0;
+
             let word = loop {
-                let word = random_word(letters, rng.gen_range(length.clone()));
+                // Give up - Scheme is probably over-constrained
+                if attempts >= max_attempts {
+                    return Err(Error::GenerationFailed {
+                        attempts,
+                        rejections: self.format_rejections(&rejections),
+                    });
+                }
+                attempts += 1;
+
+                let word = make_word(rng.gen_range(length.clone()));
 
-                if validate_test(&word, &self.rules).is_valid() {
-                    break word;
+                match validate_test(&word, &self.rules) {
+                    ValidStatus::Valid => break word,
+                    ValidStatus::Invalid { rule, .. }
+                    | ValidStatus::Timeout { rule, .. }
+                    | ValidStatus::MatchError { rule, .. } => rejections[rule] += 1,
                 }
             };
 
@@ -30,17 +132,137 @@ impl Phonet {
 
         Ok(words)
     }
+
+    /// List rules which rejected the most generated words, as a string
+    fn format_rejections(&self, rejections: &[usize]) -> String {
+        let mut rejections: Vec<(usize, usize)> = rejections
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        rejections.sort_by_key(|(_, count)| Reverse(*count));
+
+        rejections
+            .into_iter()
+            .take(MAX_REJECTION_RULES)
+            .map(|(rule, count)| {
+                let reason = self.rules[rule]
+                    .reason_ref
+                    .and_then(|reason| self.reasons.get(reason))
+                    .map(|reason| format!(" ({reason})"))
+                    .unwrap_or_default();
+
+                format!("rule #{rule}{reason}: {count}")
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Get letters of the 'any' class (`$_`), without regex syntax characters
+    pub(crate) fn any_letters(&self) -> Result<Vec<char>, Error> {
+        let Some(value) = self.classes.get("_") else {
+            return Err(Error::MissingAnyClass);
+        };
+
+        let mut letters: Vec<char> = value
+            .chars()
+            .filter(|ch| !r"\.+*?()|[]{}^$:-".contains(*ch))
+            .collect();
+        letters.sort_unstable();
+        letters.dedup();
+
+        Ok(letters)
+    }
+}
+
+/// Frequencies of letters, and of letters following each letter, in a list of words
+struct Bigrams {
+    /// Letters which start words
+    starts: Vec<(char, usize)>,
+    /// Letters which follow each letter
+    follows: HashMap<char, Vec<(char, usize)>>,
+    /// All letters
+    letters: Vec<(char, usize)>,
+}
+
+impl Bigrams {
+    /// Count frequencies of letters in words
+    fn new<'a>(words: impl Iterator<Item = &'a str>) -> Self {
+        let mut starts = HashMap::new();
+        let mut follows: HashMap<char, HashMap<char, usize>> = HashMap::new();
+        let mut letters = HashMap::new();
+
+        for word in words {
+            let mut prev = None;
+
+            for ch in word.chars() {
+                *letters.entry(ch).or_insert(0) += 1;
+
+                match prev {
+                    None => *starts.entry(ch).or_insert(0) += 1,
+                    Some(prev) => *follows.entry(prev).or_default().entry(ch).or_insert(0) += 1,
+                }
+
+                prev = Some(ch);
+            }
+        }
+
+        Bigrams {
+            starts: sorted_counts(starts),
+            follows: follows
+                .into_iter()
+                .map(|(ch, counts)| (ch, sorted_counts(counts)))
+                .collect(),
+            letters: sorted_counts(letters),
+        }
+    }
+
+    /// Returns `true` if no letters were counted
+    fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    /// Generate random word with given length, choosing each letter by frequency after previous letter
+    ///
+    /// If no letter was found after previous letter, any letter is chosen by frequency
+    fn random_word(&self, length: usize) -> String {
+        let mut word = String::new();
+        let mut rng = rand::thread_rng();
+        let mut prev = None;
+
+        for _ in 0..length {
+            let choices = match prev {
+                None => &self.starts,
+                Some(prev) => self.follows.get(&prev).unwrap_or(&self.letters),
+            };
+
+            if let Ok((ch, _)) = choices.choose_weighted(&mut rng, |(_, count)| *count) {
+                word.push(*ch);
+                prev = Some(*ch);
+            }
+        }
+
+        word
+    }
+}
+
+/// Convert counts of letters to list, sorted by letter
+fn sorted_counts(counts: HashMap<char, usize>) -> Vec<(char, usize)> {
+    let mut counts: Vec<(char, usize)> = counts.into_iter().collect();
+    counts.sort_unstable();
+    counts
 }
 
 /// Generate random word with given characters and given length
-/// TODO replace `letters` type with impl IntoIterator with char type or something
-fn random_word(letters: &str, length: usize) -> String {
-    let chars: Vec<char> = letters.chars().collect();
+fn random_word(letters: &[char], length: usize) -> String {
     let mut word = String::new();
     let mut rng = rand::thread_rng();
 
     for _ in 0..length {
-        word.push(*chars.choose(&mut rng).unwrap());
+        if let Some(ch) = letters.choose(&mut rng) {
+            word.push(*ch);
+        }
     }
 
     word
@@ -0,0 +1,286 @@
+// * This file is just for the binary
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use phonet::Results;
+use serde_json::{json, Value};
+
+/// End of name of history file, saved next to the scheme file, if no path is given
+const HISTORY_SUFFIX: &str = "phonet-history.json";
+
+/// Width of pass rate bar, in characters
+const BAR_WIDTH: usize = 30;
+
+/// Summary of a single run, saved in history file
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    /// Seconds since Unix epoch, when tests were run
+    pub timestamp: u64,
+    /// Amount of passed tests
+    pub passed: usize,
+    /// Amount of failed tests
+    pub failed: usize,
+    /// Text of reason, and count of failed tests with reason
+    pub reasons: Vec<(String, usize)>,
+}
+
+impl Entry {
+    /// Get percentage of tests which passed, from 0 to 100
+    ///
+    /// Returns `None` if no tests ran
+    fn pass_rate(&self) -> Option<f64> {
+        let total = self.passed + self.failed;
+        if total == 0 {
+            return None;
+        }
+        Some(self.passed as f64 / total as f64 * 100.0)
+    }
+}
+
+/// Get path of history file, for a scheme file
+///
+/// Uses path if given, otherwise history file next to scheme file, with name of scheme file
+///
+/// Eg. `examples/example.phonet` -> `examples/.example.phonet-history.json`
+pub fn history_path(file: &str, path: Option<&str>) -> PathBuf {
+    if let Some(path) = path {
+        return PathBuf::from(path);
+    }
+
+    let file = PathBuf::from(file);
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    file.with_file_name(format!(".{stem}.{HISTORY_SUFFIX}"))
+}
+
+/// Read every entry of history file, oldest first
+///
+/// Returns empty list if file does not exist, or is not valid. Invalid entries are skipped
+pub fn read_history(path: &Path) -> Vec<Entry> {
+    let Ok(file) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let Ok(history) = serde_json::from_str::<Value>(&file) else {
+        return Vec::new();
+    };
+
+    history
+        .as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    Some(Entry {
+                        timestamp: entry["timestamp"].as_u64()?,
+                        passed: entry["passed"].as_u64()? as usize,
+                        failed: entry["failed"].as_u64()? as usize,
+                        reasons: entry["reasons"]
+                            .as_array()?
+                            .iter()
+                            .filter_map(|reason| {
+                                Some((
+                                    reason["reason"].as_str()?.to_string(),
+                                    reason["count"].as_u64()? as usize,
+                                ))
+                            })
+                            .collect(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Add summary of results to end of history file, creating file if it does not exist
+pub fn append_history(path: &Path, results: &Results) -> io::Result<()> {
+    let mut history = match fs::read_to_string(path) {
+        Ok(file) => match serde_json::from_str::<Value>(&file) {
+            Ok(Value::Array(entries)) => entries,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("History file '{}' is not a JSON array", path.display()),
+                ))
+            }
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let reasons: Vec<Value> = results
+        .stats()
+        .reasons
+        .into_iter()
+        .map(|(reason, count)| json!({ "reason": reason, "count": count }))
+        .collect();
+
+    history.push(json!({
+        "timestamp": timestamp,
+        "passed": results.pass_count(),
+        "failed": results.fail_count,
+        "reasons": reasons,
+    }));
+
+    fs::write(path, Value::Array(history).to_string())
+}
+
+/// Display pass rate of each entry as a bar, with change from previous entry, to standard output
+///
+/// Only the last `count` entries are displayed. Reasons of the last entry are listed after
+pub fn display_trend(history: &[Entry], count: usize, no_color: bool) {
+    if history.is_empty() {
+        if no_color {
+            println!("No runs in history. Run tests with `--history` to save a run");
+        } else {
+            println!("\x1b[33mNo runs in history. Run tests with `--history` to save a run\x1b[0m");
+        }
+        return;
+    }
+
+    let start = history.len().saturating_sub(count);
+
+    if no_color {
+        println!("Pass rate of last {} runs:", history.len() - start);
+    } else {
+        println!(
+            "\x1b[34mPass rate of last \x1b[0;1m{}\x1b[0;34m runs:\x1b[0m",
+            history.len() - start
+        );
+    }
+
+    for (index, entry) in history.iter().enumerate().skip(start) {
+        let date = format_timestamp(entry.timestamp);
+
+        let Some(rate) = entry.pass_rate() else {
+            if no_color {
+                println!("  {date}  No tests ran");
+            } else {
+                println!("  \x1b[2m{date}\x1b[0m  \x1b[33mNo tests ran\x1b[0m");
+            }
+            continue;
+        };
+
+        // Change from previous run
+        let change = match index
+            .checked_sub(1)
+            .and_then(|previous| history[previous].pass_rate())
+        {
+            Some(previous) if (rate - previous).abs() >= 0.05 => {
+                format!("{:+.1}", rate - previous)
+            }
+            _ => String::new(),
+        };
+
+        let filled = (rate / 100.0 * BAR_WIDTH as f64).round() as usize;
+        let empty = BAR_WIDTH - filled;
+
+        if no_color {
+            println!(
+                "  {date}  {}{}  {rate:>5.1}%  {change:>5}  ({} passed, {} failed)",
+                "#".repeat(filled),
+                "-".repeat(empty),
+                entry.passed,
+                entry.failed,
+            );
+        } else {
+            println!(
+                "  \x1b[2m{date}\x1b[0m  \x1b[32m{}\x1b[31m{}\x1b[0m  \x1b[1m{rate:>5.1}%\x1b[0m  \x1b[{color}{change:>5}\x1b[0m  \x1b[2m({} passed, {} failed)\x1b[0m",
+                "█".repeat(filled),
+                "░".repeat(empty),
+                entry.passed,
+                entry.failed,
+                color = if change.starts_with('-') { "31m" } else { "32m" },
+            );
+        }
+    }
+
+    // Reasons of last run
+    let Some(last) = history.last() else {
+        return;
+    };
+    if last.reasons.is_empty() {
+        return;
+    }
+    if no_color {
+        println!("Reasons of failed tests, in last run:");
+    } else {
+        println!("\x1b[34mReasons of failed tests, in last run:\x1b[0m");
+    }
+    for (reason, count) in &last.reasons {
+        if no_color {
+            println!("  {count:>4}  {reason}");
+        } else {
+            println!("  \x1b[1m{count:>4}\x1b[0m  \x1b[3m{reason}\x1b[0m");
+        }
+    }
+}
+
+/// Format seconds since Unix epoch as a UTC date and time
+///
+/// Eg. `2023-01-31 14:05`
+fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+
+    // Convert days since epoch to civil date
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400;
+    let year = if month <= 2 { year + 1 } else { year };
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_path_works() {
+        assert_eq!(
+            history_path("examples/example.phonet", None),
+            PathBuf::from("examples/.example.phonet-history.json")
+        );
+        // Schemes in same directory
+        assert_ne!(
+            history_path("examples/example.phonet", None),
+            history_path("examples/other.phonet", None)
+        );
+        assert_eq!(
+            history_path("phonet", Some("history.json")),
+            PathBuf::from("history.json")
+        );
+    }
+
+    #[test]
+    fn format_timestamp_works() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(1_675_173_900), "2023-01-31 14:05");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00");
+        assert_eq!(format_timestamp(1_798_761_599), "2026-12-31 23:59");
+    }
+}
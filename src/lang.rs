@@ -0,0 +1,61 @@
+// * This file is just for the binary
+
+use std::{collections::HashMap, fs};
+
+use serde_json::Value;
+
+/// Get default path of translations file, for a scheme file
+///
+/// Eg. `phonet.lang.json` or `examples/example.phonet.lang.json`
+pub fn translations_path(file: &str) -> String {
+    format!("{file}.lang.json")
+}
+
+/// Read translations of a single language from translations file
+pub fn read_translations(path: &str, lang: &str) -> Result<HashMap<String, String>, String> {
+    let file = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read translations file '{path}': {err}"))?;
+
+    parse_translations(&file, lang).map_err(|err| format!("{err}, in '{path}'"))
+}
+
+/// Parse translations of a single language, from JSON object of each language
+///
+/// Eg. `{ "es": { "No 'x'": "Sin 'x'" } }`
+fn parse_translations(file: &str, lang: &str) -> Result<HashMap<String, String>, String> {
+    let translations: Value =
+        serde_json::from_str(file).map_err(|err| format!("Invalid translations file: {err}"))?;
+
+    let Some(translations) = translations.get(lang).and_then(Value::as_object) else {
+        return Err(format!("No translations for language '{lang}'"));
+    };
+
+    translations
+        .iter()
+        .map(|(key, value)| match value.as_str() {
+            Some(value) => Ok((key.to_string(), value.to_string())),
+            None => Err(format!("Translation of '{key}' must be text")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_translations_works() {
+        let file = r#"{ "es": { "No 'x'": "Sin 'x'" }, "fr": { "No 'x'": 1 } }"#;
+
+        let translations = parse_translations(file, "es").unwrap();
+        assert_eq!(translations.len(), 1);
+        assert_eq!(
+            translations.get("No 'x'").map(String::as_str),
+            Some("Sin 'x'")
+        );
+
+        assert!(parse_translations(file, "fr").is_err());
+        assert!(parse_translations(file, "de").is_err());
+        assert!(parse_translations("{", "es").is_err());
+    }
+}
@@ -1,12 +1,49 @@
+/// Find which class matched each part of a word
+mod align;
+/// Compare validity of words with two schemes
+mod compare;
+/// Breakdown of single word against every rule
+mod explain;
+/// Find accidental gaps in a corpus of words
+mod gaps;
 /// Generate random word
 mod generate;
+/// Read and decode `phonet` files
+mod load;
 /// Handles all parsing of `phonet` files
 mod parse;
+/// Machine-readable reports of test results
+mod report;
 /// Handles running of tests
 mod run;
+/// Consistency checks of a scheme
+mod selftest;
+/// Strategies for property testing with `proptest`
+#[cfg(feature = "proptest")]
+mod strategy;
+/// Validate words line by line, from a reader
+mod stream;
+/// Translate reasons to another language
+mod translate;
 /// Holds simple types and structs
 mod types;
+/// Usage of classes by rules and tests
+mod usage;
 
-pub use parse::Phonet;
-pub use run::{Results, ValidStatus};
-pub use types::{DisplayLevel, Error, FailReason, TestDefinition, TestResult};
+pub use align::{Alignment, RuleAlignment, Segment};
+pub use compare::Difference;
+pub use explain::{Explanation, RuleTrace, WordAnalysis};
+pub use gaps::Gap;
+pub use generate::DEFAULT_GENERATE_ATTEMPTS;
+pub use load::{decode_scheme, read_scheme, EXAMPLE_SCHEME};
+pub use parse::{MergePolicy, Phonet};
+pub use report::{ReportFormat, Section, Stats};
+pub use run::{IntentCounts, Results, ValidStatus};
+pub use selftest::{CheckStatus, SelfTest, SelfTestCheck};
+pub use stream::StreamCounts;
+pub use types::{
+    Diacritics, DisplayLevel, DisplayOptions, Error, FailReason, Limits, Note, NoteFilter,
+    NoteLevel, ParseOptions, Phoneme, RunOptions, TestDefinition, TestResult, Validator,
+    ValidatorFn, Warning,
+};
+pub use usage::ClassUsage;
@@ -0,0 +1,227 @@
+use std::{fs, path::Path};
+
+use crate::{types::Error, Phonet};
+
+/// Example scheme, which shows most syntax
+///
+/// Same as `examples/example.phonet`. Created with `phonet init`
+pub const EXAMPLE_SCHEME: &str = include_str!("../examples/example.phonet");
+
+impl Phonet {
+    /// Parse example scheme
+    ///
+    /// See `EXAMPLE_SCHEME`
+    pub fn example() -> Phonet {
+        Phonet::parse(EXAMPLE_SCHEME).expect("Example scheme should always parse")
+    }
+}
+
+/// Read scheme file as text
+///
+/// See `decode_scheme`
+pub fn read_scheme(path: impl AsRef<Path>) -> Result<String, Error> {
+    let path = path.as_ref();
+
+    let bytes = fs::read(path).map_err(|source| Error::ReadFile {
+        path: path.display().to_string(),
+        source,
+    })?;
+
+    decode_scheme(&bytes)
+}
+
+/// Decode bytes of scheme file as text
+///
+/// - Byte order marks are removed
+/// - UTF-16 (little or big endian) is detected and converted, with or without a byte order mark
+/// - Line endings (`\r\n` and `\r`) are converted to `\n`
+pub fn decode_scheme(bytes: &[u8]) -> Result<String, Error> {
+    let text = match bytes {
+        // UTF-8 with byte order mark
+        [0xEF, 0xBB, 0xBF, rest @ ..] => decode_utf8(rest, 3)?,
+
+        // UTF-16 with byte order mark
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, false, 2)?,
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, true, 2)?,
+
+        // No byte order mark
+        _ => match guess_utf16(bytes) {
+            Some(big_endian) => decode_utf16(bytes, big_endian, 0)?,
+            None => decode_utf8(bytes, 0)?,
+        },
+    };
+
+    Ok(text.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+/// Decode bytes as UTF-8
+///
+/// `offset` is added to position of invalid byte in error
+fn decode_utf8(bytes: &[u8], offset: usize) -> Result<String, Error> {
+    String::from_utf8(bytes.to_vec()).map_err(|err| Error::InvalidEncoding {
+        encoding: "UTF-8",
+        position: offset + err.utf8_error().valid_up_to(),
+    })
+}
+
+/// Decode bytes as UTF-16
+///
+/// `offset` is added to position of invalid byte in error
+fn decode_utf16(bytes: &[u8], big_endian: bool, offset: usize) -> Result<String, Error> {
+    let encoding = if big_endian { "UTF-16BE" } else { "UTF-16LE" };
+
+    // Odd amount of bytes
+    if !bytes.len().is_multiple_of(2) {
+        return Err(Error::InvalidEncoding {
+            encoding,
+            position: offset + bytes.len() - 1,
+        });
+    }
+
+    let units = bytes.chunks_exact(2).map(|pair| {
+        if big_endian {
+            u16::from_be_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_le_bytes([pair[0], pair[1]])
+        }
+    });
+
+    let mut text = String::new();
+    for (index, ch) in char::decode_utf16(units).enumerate() {
+        match ch {
+            Ok(ch) => text.push(ch),
+            Err(_) => {
+                return Err(Error::InvalidEncoding {
+                    encoding,
+                    position: offset + index * 2,
+                })
+            }
+        }
+    }
+
+    Ok(text)
+}
+
+/// Guess if bytes without byte order mark are UTF-16, by position of null bytes
+///
+/// Bytes must also decode as valid UTF-16
+///
+/// Returns `Some(true)` for big endian, `Some(false)` for little endian, or `None` if not UTF-16
+fn guess_utf16(bytes: &[u8]) -> Option<bool> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+
+    // Count null bytes in even and odd positions
+    let (mut even, mut odd) = (0, 0);
+    for (index, byte) in bytes.iter().enumerate() {
+        if *byte == 0 {
+            if index % 2 == 0 {
+                even += 1;
+            } else {
+                odd += 1;
+            }
+        }
+    }
+
+    // Mostly ASCII text has a null byte in most characters, on the same side
+    // Some characters have a null byte on the other side (Eg. U+0300), so only most must match
+    let half = bytes.len() / 2;
+    let big_endian = if odd > half / 2 && even <= odd / 4 {
+        false
+    } else if even > half / 2 && odd <= even / 4 {
+        true
+    } else {
+        return None;
+    };
+
+    decode_utf16(bytes, big_endian, 0)
+        .is_ok()
+        .then_some(big_endian)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode string as UTF-16 bytes
+    fn utf16(s: &str, big_endian: bool) -> Vec<u8> {
+        s.encode_utf16()
+            .flat_map(|unit| {
+                if big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decode_scheme_works() {
+        let text = "$C = [pt]\n+ ^<C>ã$\n";
+
+        assert_eq!(decode_scheme(text.as_bytes()).unwrap(), text);
+
+        // Byte order mark
+        let bytes = [&[0xEF, 0xBB, 0xBF][..], text.as_bytes()].concat();
+        assert_eq!(decode_scheme(&bytes).unwrap(), text);
+
+        // Line endings
+        assert_eq!(
+            decode_scheme(text.replace('\n', "\r\n").as_bytes()).unwrap(),
+            text
+        );
+        assert_eq!(decode_scheme(b"a\rb").unwrap(), "a\nb");
+
+        // UTF-16, with and without byte order mark
+        for big_endian in [false, true] {
+            let bytes = utf16(text, big_endian);
+            assert_eq!(decode_scheme(&bytes).unwrap(), text);
+
+            let bom = if big_endian {
+                [0xFE, 0xFF]
+            } else {
+                [0xFF, 0xFE]
+            };
+            let bytes = [&bom[..], bytes.as_slice()].concat();
+            assert_eq!(decode_scheme(&bytes).unwrap(), text);
+        }
+
+        // UTF-16 without byte order mark, with characters which have a null byte on the other side
+        let text = "$V = [ae\u{300}i\u{300}]\n+ ^<V>\u{300}?$\n";
+        for big_endian in [false, true] {
+            let bytes = utf16(text, big_endian);
+            assert_eq!(decode_scheme(&bytes).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn decode_scheme_errors() {
+        assert!(matches!(
+            decode_scheme(b"+ ^a\xFF$"),
+            Err(Error::InvalidEncoding {
+                encoding: "UTF-8",
+                position: 4,
+            })
+        ));
+
+        // Unpaired surrogate
+        assert!(matches!(
+            decode_scheme(&[0xFF, 0xFE, b'a', 0, 0x00, 0xD8]),
+            Err(Error::InvalidEncoding {
+                encoding: "UTF-16LE",
+                position: 4,
+            })
+        ));
+
+        // Odd amount of bytes
+        assert!(matches!(
+            decode_scheme(&[0xFF, 0xFE, b'a', 0, b'b']),
+            Err(Error::InvalidEncoding {
+                encoding: "UTF-16LE",
+                position: 4,
+            })
+        ));
+    }
+}
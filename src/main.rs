@@ -1,22 +1,329 @@
 mod args;
+mod bench;
+mod export;
+mod history;
+mod lang;
+mod multi;
+mod sample;
+mod state;
 
-use std::fs;
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::Path,
+    thread,
+    time::Duration,
+};
 
-use args::Args;
+use args::{Args, Command};
 use clap::Parser;
-use phonet::{TestDefinition, Phonet};
+use phonet::{
+    DisplayOptions, FailReason, ParseOptions, Phonet, ReportFormat, RunOptions, TestDefinition,
+    TestResult, DEFAULT_GENERATE_ATTEMPTS,
+};
+
+/// Exit code if scheme has no rules, and no tests ran
+const EXIT_EMPTY: i32 = 2;
+/// Exit code if scheme has rules, but no tests ran
+const EXIT_NO_TESTS: i32 = 3;
+/// Exit code if tests ran, but scheme has no rules, so every word is valid
+const EXIT_NO_RULES: i32 = 4;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
+    // Compare two files, instead of running tests
+    if let Some(Command::Compare {
+        first,
+        second,
+        words,
+    }) = &args.command
+    {
+        let differences = compare(first, second, words.as_deref(), args.no_color)?;
+        if differences > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Display pass rate of saved runs, instead of running tests
+    if let Some(Command::Trend { history, last }) = &args.command {
+        let path = history::history_path(&args.file, history.as_deref());
+        history::display_trend(&history::read_history(&path), *last, args.no_color);
+        return Ok(());
+    }
+
+    // Create file with example scheme, instead of running tests
+    if let Some(Command::Init { force }) = &args.command {
+        if !force && Path::new(&args.file).exists() {
+            return Err(format!(
+                "File '{}' already exists. Use `--force` to replace it",
+                args.file
+            )
+            .into());
+        }
+        fs::write(&args.file, phonet::EXAMPLE_SCHEME)?;
+
+        if args.no_color {
+            println!("Created example scheme '{}'", args.file);
+        } else {
+            println!(
+                "\x1b[34mCreated example scheme '\x1b[0;3m{}\x1b[0;34m'\x1b[0m",
+                args.file
+            );
+        }
+        return Ok(());
+    }
+
+    // Run every scheme file in directory, instead of a single file
+    if Path::new(&args.file).is_dir() {
+        let unsupported = multi::unsupported_options(&args);
+        if !unsupported.is_empty() {
+            return Err(format!(
+                "Cannot use {} with a directory. Run each file on its own instead",
+                unsupported.join(", ")
+            )
+            .into());
+        }
+
+        let errors = multi::run_directory(Path::new(&args.file), &args)?;
+        if errors > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Read file
-    let file = fs::read_to_string(&args.file)?;
+    let file = phonet::read_scheme(&args.file)?;
+
+    // Benchmark scheme, instead of running tests
+    if let Some(Command::Bench { rounds }) = args.command {
+        bench::bench(&file, rounds, args.no_color)?;
+        return Ok(());
+    }
+
+    // Check consistency of scheme, instead of running tests
+    if let Some(Command::Selftest) = args.command {
+        // Compile rules in self-test, so every invalid rule is listed
+        let scheme = Phonet::parse_with(
+            &file,
+            &ParseOptions {
+                lazy: true,
+                backtrack_limit: args.backtrack_limit,
+                defines: args.define.clone(),
+                extra_rules: args.extra_rule.clone(),
+                ..Default::default()
+            },
+        );
+
+        let passed = match scheme {
+            Ok(scheme) => {
+                let self_test = scheme.self_test();
+                self_test.display(args.no_color);
+                self_test.passed()
+            }
+            Err(err) => {
+                if args.no_color {
+                    println!("Error: {err}\nSelf-test failed!");
+                } else {
+                    println!(
+                        "\x1b[31;1mError:\x1b[0;31m {err}\x1b[0m\n\x1b[31;1;3mSelf-test failed!\x1b[0m"
+                    );
+                }
+                false
+            }
+        };
+
+        if !passed {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Reports printed to standard output, instead of displaying results
+    let mut printed_reports: Vec<ReportFormat> = args
+        .report
+        .iter()
+        .filter(|report| report.path.is_none())
+        .map(|report| report.format)
+        .collect();
+    if args.canonical {
+        printed_reports.push(ReportFormat::Canonical);
+    }
+
+    // Quickfix report - Print problems in the same format as failed tests
+    let quickfix = printed_reports
+        .iter()
+        .any(|format| matches!(format, ReportFormat::Quickfix));
 
     // Parse file
-    let mut scheme = Phonet::parse(&file)
+    let parsed = Phonet::parse_with(
+        &file,
+        &ParseOptions {
+            lazy: args.lazy,
+            backtrack_limit: args.backtrack_limit,
+            defines: args.define.clone(),
+            extra_rules: args.extra_rule.clone(),
+            ..Default::default()
+        },
+    );
+    if let (true, Err(err)) = (quickfix, &parsed) {
+        print!("{}", err.quickfix(&args.file));
+        std::process::exit(1);
+    }
+    let mut scheme = parsed
         .map_err(|err| err.to_string())
         .expect("Failed to parse file");
 
+    // Remove duplicate tests
+    if args.dedup {
+        scheme.dedup_tests();
+    }
+
+    // Treat warnings as errors
+    if args.deny_warnings {
+        let denied = scheme.deny_warnings();
+        if let (true, Err(err)) = (quickfix, &denied) {
+            print!("{}", err.quickfix(&args.file));
+            std::process::exit(1);
+        }
+        scheme = denied
+            .map_err(|err| err.to_string())
+            .expect("Failed to parse file");
+    }
+
+    // Translate reasons
+    if let Some(lang) = &args.lang {
+        let path = match &args.translations {
+            Some(path) => path.to_string(),
+            None => lang::translations_path(&args.file),
+        };
+        let missing = scheme.translate(&lang::read_translations(&path, lang)?);
+
+        for reason in missing {
+            if args.no_color {
+                eprintln!("Warning: No '{lang}' translation for reason '{reason}'");
+            } else {
+                eprintln!(
+                    "\x1b[33;1mWarning:\x1b[0;33m No '{lang}' translation for reason '\x1b[3m{reason}\x1b[0;33m'\x1b[0m"
+                );
+            }
+        }
+    }
+
+    // Display warnings
+    for warning in &scheme.warnings {
+        if quickfix {
+            print!("{}", warning.quickfix(&args.file));
+        } else if args.no_color {
+            eprintln!("Warning: {warning}");
+        } else {
+            eprintln!("\x1b[33;1mWarning:\x1b[0;33m {warning}\x1b[0m");
+        }
+    }
+
+    // Only display warnings, instead of running tests
+    if let Some(Command::Lint) = &args.command {
+        display_class_usage(&scheme, args.no_color);
+
+        let count = scheme.warnings.len();
+        if count == 0 {
+            if args.no_color {
+                println!("No problems found.");
+            } else {
+                println!("\x1b[32;1;3mNo problems found.\x1b[0m");
+            }
+            return Ok(());
+        }
+
+        if args.no_color {
+            println!(
+                "{count} warning{s} found!",
+                s = if count == 1 { "" } else { "s" }
+            );
+        } else {
+            println!(
+                "\x1b[33;1;3m{count} warning{s} found!\x1b[0m",
+                s = if count == 1 { "" } else { "s" }
+            );
+        }
+        std::process::exit(1);
+    }
+
+    // Explain single word, instead of running tests
+    if let Some(Command::Explain { word }) = &args.command {
+        scheme.explain(word).display(args.no_color);
+        return Ok(());
+    }
+
+    // Find accidental gaps in corpus, instead of running tests
+    if let Some(Command::Gaps { corpus, length }) = &args.command {
+        let corpus = match corpus {
+            Some(path) => fs::read_to_string(path)?
+                .lines()
+                .map(str::trim)
+                .filter(|word| !word.is_empty())
+                .map(String::from)
+                .collect(),
+            None => scheme.corpus(),
+        };
+
+        let gaps = scheme.gaps(&corpus, *length);
+
+        if gaps.is_empty() {
+            if args.no_color {
+                println!("No gaps found.");
+            } else {
+                println!("\x1b[32;1;3mNo gaps found.\x1b[0m");
+            }
+            return Ok(());
+        }
+
+        if args.no_color {
+            println!("{} gaps of length {length}:", gaps.len());
+        } else {
+            println!(
+                "\x1b[34mGaps of length \x1b[0;1m{length}\x1b[0;34m: \x1b[0;1m{}\x1b[0m",
+                gaps.len()
+            );
+        }
+
+        for gap in gaps {
+            if args.no_color {
+                println!("  {}  (eg. {})", gap.sequence, gap.example);
+            } else {
+                println!(
+                    "  \x1b[33m{}\x1b[0m  \x1b[2m(eg. \x1b[0;3m{}\x1b[0;2m)\x1b[0m",
+                    gap.sequence, gap.example
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    // Validate words from file or standard input, instead of running tests
+    if let Some(words) = &args.words {
+        let stdout = io::stdout().lock();
+        let counts = if words == "-" {
+            scheme.validate_stream(io::stdin().lock(), BufWriter::new(stdout))?
+        } else {
+            scheme.validate_stream(BufReader::new(File::open(words)?), BufWriter::new(stdout))?
+        };
+
+        if args.no_color {
+            eprintln!("{} valid, {} invalid", counts.valid, counts.invalid);
+        } else {
+            eprintln!(
+                "\x1b[32;1m{} valid\x1b[0m, \x1b[{color}1m{} invalid\x1b[0m",
+                counts.valid,
+                counts.invalid,
+                color = if counts.invalid == 0 { "" } else { "31;" },
+            );
+        }
+        return Ok(());
+    }
+
     // Use CLI tests if given
     if let Some(tests) = args.tests {
         scheme.tests = tests
@@ -24,10 +331,49 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .map(|x| TestDefinition::Test {
                 intent: true,
                 word: x.to_string(),
+                label: None,
+                target: None,
+                line: None,
             })
             .collect();
     }
 
+    // Only run tests which failed last time
+    let state_path = state::state_path(&args.file);
+    if args.rerun_failed {
+        state::filter_failed(&mut scheme.tests, &state::read_failed(&state_path));
+    }
+
+    // Only run random subset of tests
+    if let Some(count) = args.sample {
+        let seed = args.seed.unwrap_or_else(rand::random);
+        let total = sample::sample_tests(&mut scheme.tests, count, seed);
+
+        if args.no_color {
+            eprintln!(
+                "Sampled {} of {total} tests, with seed {seed}",
+                scheme.tests.len()
+            );
+        } else {
+            eprintln!(
+                "\x1b[34mSampled \x1b[0;1m{}\x1b[0;34m of \x1b[0;1m{total}\x1b[0;34m tests, with seed \x1b[0;1m{seed}\x1b[0m",
+                scheme.tests.len()
+            );
+        }
+    }
+
+    // Run tests in random order
+    if let Some(seed) = args.shuffle {
+        let seed = seed.unwrap_or_else(rand::random);
+        sample::shuffle_tests(&mut scheme.tests, seed);
+
+        if args.no_color {
+            eprintln!("Shuffled tests, with seed {seed}");
+        } else {
+            eprintln!("\x1b[34mShuffled tests, with seed \x1b[0;1m{seed}\x1b[0m");
+        }
+    }
+
     // Minify file
     if let Some(do_tests) = args.minify {
         fs::write(
@@ -36,16 +382,85 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         )?;
     }
 
-    // Run tests and display
-    let test_count = scheme.test_count();
-    if test_count > 0 {
-        if args.no_color {
-            println!("Running {} tests...", test_count);
-        } else {
-            println!("\x1b[3;33mRunning {} tests...\x1b[0m", test_count);
+    // Amount of threads to run tests on
+    let jobs = match args.jobs {
+        0 => thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+        jobs => jobs,
+    };
+    let run_options = RunOptions {
+        jobs,
+        no_cache: args.no_cache,
+    };
+
+    let results = if !printed_reports.is_empty() {
+        // Run tests and print reports
+        let results = scheme.run_with(&run_options);
+        for format in &printed_reports {
+            print!("{}", results.report_with(*format, Some(&args.file)));
         }
+        results
+    } else {
+        // Run tests and display
+        let test_count = scheme.test_count();
+        if test_count > 0 {
+            if args.no_color {
+                println!("Running {} tests...", test_count);
+            } else {
+                println!("\x1b[3;33mRunning {} tests...\x1b[0m", test_count);
+            }
+        }
+        let results = scheme.run_with(&run_options);
+        results.display_with(&DisplayOptions {
+            display_level: args.display_level,
+            no_color: args.no_color,
+            verbose_failures: args.verbose_failures,
+            filename: Some(args.file.clone()),
+            slow_threshold: args.slow.map(Duration::from_millis),
+            slowest: args.slowest,
+            notes: args.notes,
+        });
+
+        // Display which class matched each part of words which should be invalid
+        if args.align {
+            for item in &results.list {
+                if let TestResult::Test {
+                    word,
+                    reason: FailReason::ShouldBeInvalid,
+                    ..
+                } = item
+                {
+                    scheme.align(word).display(args.no_color);
+                }
+            }
+        }
+
+        results
+    };
+
+    // Save reports to files
+    for report in &args.report {
+        if let Some(path) = &report.path {
+            fs::write(path, results.report_with(report.format, Some(&args.file)))?;
+        }
+    }
+
+    // Save failed tests for next run
+    if args.rerun_failed {
+        state::write_failed(&state_path, &results)?;
+    }
+
+    // Save summary of run to history
+    if let Some(path) = &args.history {
+        history::append_history(
+            &history::history_path(&args.file, path.as_deref()),
+            &results,
+        )?;
+    }
+
+    // Print single summary line to standard error
+    if args.summary_line {
+        eprintln!("{}", results.summary_line());
     }
-    scheme.run().display(args.display_level, args.no_color);
 
     // Generate and display random words, if CLI arg given
     if let Some(count) = args.generate {
@@ -55,6 +470,33 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let length = args.generate_min_len.unwrap_or(3)..args.generate_max_len.unwrap_or(14);
 
         if count > 0 {
+            // Generate words
+            let attempts = args.generate_attempts.unwrap_or(DEFAULT_GENERATE_ATTEMPTS);
+            let words = if args.weighted {
+                scheme.generate_weighted_with_attempts(count, length, attempts)
+            } else {
+                scheme.generate_with_attempts(count, length, attempts)
+            };
+            let words = words
+                .map_err(|err| err.to_string())
+                .expect("Could not generate words");
+
+            // Save words to file, instead of printing
+            if let Some(output) = &args.output {
+                let format = export::ExportFormat::from_path(output);
+                fs::write(output, export::export_words(&scheme, &words, format))?;
+
+                if args.no_color {
+                    println!("Saved {} words to '{output}'", words.len());
+                } else {
+                    println!(
+                        "\x1b[34mSaved {} words to '\x1b[0;3m{output}\x1b[0;34m'\x1b[0m",
+                        words.len()
+                    );
+                }
+                return Ok(());
+            }
+
             if args.no_color {
                 println!(
                     "Randomly generated word{s}:",
@@ -67,12 +509,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 );
             }
 
-            // Generate words
-            let words = scheme
-                .generate(count, length)
-                .map_err(|err| err.to_string())
-                .expect("Could not generate words");
-
             // Print words
             for word in words {
                 if args.no_color {
@@ -82,11 +518,158 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
+        return Ok(());
+    }
+
+    // Scheme is missing rules or tests
+    // Not checked when generating words, as rules and tests are not needed
+    match (scheme.rules.is_empty(), results.test_count() == 0) {
+        (true, true) => std::process::exit(EXIT_EMPTY),
+        (false, true) => std::process::exit(EXIT_NO_TESTS),
+        (true, false) => std::process::exit(EXIT_NO_RULES),
+        (false, false) => (),
     }
 
     Ok(())
 }
 
+/// Display amount of rules and test segments which use each class
+///
+/// Classes which never influence a result are highlighted
+fn display_class_usage(scheme: &Phonet, no_color: bool) {
+    let usage = scheme.class_usage();
+    if usage.is_empty() {
+        return;
+    }
+
+    if no_color {
+        println!("Class usage:");
+    } else {
+        println!("\x1b[34mClass usage:\x1b[0m");
+    }
+
+    // Get maximum length of all class names
+    let max_name_len = usage
+        .iter()
+        .map(|class| class.name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    for class in &usage {
+        let space = " ".repeat(max_name_len - class.name.chars().count());
+        let counts = format!(
+            "{:>4} rule{}  {:>6} segment{}",
+            class.rules,
+            if class.rules == 1 { " " } else { "s" },
+            class.segments,
+            if class.segments == 1 { " " } else { "s" },
+        );
+
+        if !class.is_unused() {
+            if no_color {
+                println!("  {}{space}  {counts}", class.name);
+            } else {
+                println!("  \x1b[1m{}\x1b[0m{space}  {counts}", class.name);
+            }
+        } else if no_color {
+            println!(
+                "  {}{space}  {counts}  (never influences a result)",
+                class.name
+            );
+        } else {
+            println!(
+                "  \x1b[33;1m{}\x1b[0m{space}  \x1b[33m{counts}  (never influences a result)\x1b[0m",
+                class.name
+            );
+        }
+    }
+}
+
+/// Validate words with two files, and display words with different validity
+///
+/// Returns amount of words which differ
+fn compare(
+    first: &str,
+    second: &str,
+    words: Option<&str>,
+    no_color: bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let parse = |path: &str| -> Result<Phonet, Box<dyn std::error::Error>> {
+        Ok(Phonet::parse(&phonet::read_scheme(path)?)
+            .map_err(|err| format!("Failed to parse file '{path}': {err}"))?)
+    };
+    let first_scheme = parse(first)?;
+    let second_scheme = parse(second)?;
+
+    // Words from file or standard input, or words of tests of both files
+    let list = match words {
+        Some("-") => io::read_to_string(io::stdin())?,
+        Some(path) => fs::read_to_string(path)?,
+        None => String::new(),
+    };
+    let words: Vec<&str> = match words {
+        Some(_) => list
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .collect(),
+        None => {
+            let mut seen = HashSet::new();
+            first_scheme
+                .test_words()
+                .into_iter()
+                .chain(second_scheme.test_words())
+                .filter(|word| seen.insert(*word))
+                .collect()
+        }
+    };
+
+    let differences = first_scheme.compare(&second_scheme, words.iter().copied());
+
+    if differences.is_empty() {
+        if no_color {
+            println!("All {} words have the same result.", words.len());
+        } else {
+            println!(
+                "\x1b[32;1;3mAll {} words have the same result.\x1b[0m",
+                words.len()
+            );
+        }
+        return Ok(0);
+    }
+
+    if no_color {
+        println!("{} of {} words differ:", differences.len(), words.len());
+    } else {
+        println!(
+            "\x1b[31;1;3m{} of {} words differ:\x1b[0m",
+            differences.len(),
+            words.len()
+        );
+    }
+
+    let validity = |valid: bool| if valid { "valid" } else { "invalid" };
+    for difference in &differences {
+        if no_color {
+            println!(
+                "  {}  {first}: {}, {second}: {}",
+                difference.word,
+                validity(difference.first),
+                validity(difference.second)
+            );
+        } else {
+            println!(
+                "  \x1b[3m{}\x1b[0m  \x1b[2m{first}:\x1b[0m {}\x1b[2m, {second}:\x1b[0m {}",
+                difference.word,
+                validity(difference.first),
+                validity(difference.second)
+            );
+        }
+    }
+
+    Ok(differences.len())
+}
+
 /// Adds '.min' to filename, before last file extension
 ///
 /// Returns empty string if filename is empty
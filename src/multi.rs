@@ -0,0 +1,191 @@
+// * This file is just for the binary
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use phonet::{DisplayOptions, Error, ParseOptions, Phonet, Results, RunOptions};
+
+use crate::args::Args;
+
+/// Extension of scheme files, when running a directory
+const EXTENSION: &str = "phonet";
+
+/// Find scheme files in directory, sorted by path
+///
+/// Only files with `.phonet` extension are included. Subdirectories are not searched
+pub fn scheme_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect();
+
+    files.sort();
+    Ok(files)
+}
+
+/// Get names of options given, which cannot be used with a directory
+///
+/// These options write files, filter tests, or replace the display, which is only supported for a single file
+pub fn unsupported_options(args: &Args) -> Vec<&'static str> {
+    [
+        (args.command.is_some(), "subcommands"),
+        (args.tests.is_some(), "--tests"),
+        (args.words.is_some(), "--words"),
+        (args.align, "--align"),
+        (!args.report.is_empty(), "--report"),
+        (args.canonical, "--canonical"),
+        (args.minify.is_some(), "--minify"),
+        (args.generate.is_some(), "--generate"),
+        (args.summary_line, "--summary-line"),
+        (args.lang.is_some(), "--lang"),
+        (args.history.is_some(), "--history"),
+        (args.rerun_failed, "--rerun-failed"),
+        (args.sample.is_some(), "--sample"),
+        (args.shuffle.is_some(), "--shuffle"),
+        (args.dedup, "--dedup"),
+        (args.deny_warnings, "--deny-warnings"),
+    ]
+    .into_iter()
+    .filter_map(|(given, name)| given.then_some(name))
+    .collect()
+}
+
+/// Parse and run every scheme file in directory, each on its own thread
+///
+/// Results of each file are displayed as soon as it finishes, so files are displayed in order of finishing
+///
+/// Returns amount of files which could not be read or parsed
+pub fn run_directory(dir: &Path, args: &Args) -> io::Result<usize> {
+    let files = scheme_files(dir)?;
+
+    let parse_options = ParseOptions {
+        lazy: args.lazy,
+        backtrack_limit: args.backtrack_limit,
+        defines: args.define.clone(),
+        extra_rules: args.extra_rule.clone(),
+        ..Default::default()
+    };
+    let run_options = RunOptions {
+        jobs: 1,
+        no_cache: args.no_cache,
+    };
+
+    let mut errors = 0;
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+
+        for file in &files {
+            let sender = sender.clone();
+            let (parse_options, run_options) = (&parse_options, &run_options);
+
+            scope.spawn(move || {
+                let results = phonet::read_scheme(file)
+                    .and_then(|text| Phonet::parse_with(&text, parse_options))
+                    .map(|scheme| scheme.run_with(run_options));
+
+                // Receiver is only dropped after every file is sent
+                sender
+                    .send((file, results))
+                    .expect("Failed to send results of file");
+            });
+        }
+
+        // Stop receiving when every thread has finished
+        drop(sender);
+
+        for (file, results) in receiver {
+            if !display_file(file, results, args) {
+                errors += 1;
+            }
+        }
+    });
+
+    // Final print
+    if args.no_color {
+        println!("Ran {} files, {errors} could not be parsed", files.len());
+    } else {
+        println!(
+            "\x1b[34mRan \x1b[0;1m{}\x1b[0;34m files, \x1b[0;{color}1m{errors}\x1b[0;34m could not be parsed\x1b[0m",
+            files.len(),
+            color = if errors == 0 { "" } else { "31;" },
+        );
+    }
+
+    Ok(errors)
+}
+
+/// Display results of a single file, with name of file
+///
+/// Returns `false` if file could not be read or parsed
+fn display_file(file: &Path, results: Result<Results, Error>, args: &Args) -> bool {
+    let name = file.display().to_string();
+
+    if args.no_color {
+        println!("\n{name}:");
+    } else {
+        println!("\n\x1b[34;1m{name}:\x1b[0m");
+    }
+
+    match results {
+        Ok(results) => {
+            results.display_with(&DisplayOptions {
+                display_level: args.display_level,
+                no_color: args.no_color,
+                verbose_failures: args.verbose_failures,
+                filename: Some(name),
+                slow_threshold: args.slow.map(Duration::from_millis),
+                slowest: args.slowest,
+                notes: args.notes,
+            });
+            true
+        }
+
+        Err(err) => {
+            if args.no_color {
+                println!("Error: {err}");
+            } else {
+                println!("\x1b[31;1mError:\x1b[0;31m {err}\x1b[0m");
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheme_files_works() {
+        let dir = std::env::temp_dir().join(format!("phonet-multi-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        for file in ["b.phonet", "a.phonet", "notes.txt", "nested/c.phonet"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+
+        let files = scheme_files(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files, [dir.join("a.phonet"), dir.join("b.phonet")]);
+    }
+
+    #[test]
+    fn unsupported_options_works() {
+        use clap::Parser;
+
+        let args = Args::parse_from(["phonet", "-f", "schemes", "-d", "j", "--lazy"]);
+        assert!(unsupported_options(&args).is_empty());
+
+        let args = Args::parse_from(["phonet", "-f", "schemes", "-t", "a", "-r", "json", "-m"]);
+        assert_eq!(
+            unsupported_options(&args),
+            ["--tests", "--report", "--minify"]
+        );
+    }
+}
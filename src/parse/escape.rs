@@ -0,0 +1,102 @@
+use crate::types::Error;
+
+/// Characters which must be escaped, to be used literally in regex
+const REGEX_SYNTAX: &str = r"\.+*?()|[]{}^$";
+
+/// Replace unicode escape sequences (Eg. `\u{0303}`) with the characters they represent
+///
+/// If `is_regex` is true, characters with special meaning in regex are escaped, to be used literally
+///
+/// Other escape sequences are not changed
+pub fn unescape_unicode(s: &str, line: usize, is_regex: bool) -> Result<String, Error> {
+    let mut output = String::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            output.push(ch);
+            continue;
+        }
+
+        // Not a unicode escape - Keep escape sequence as-is
+        if chars.peek() != Some(&'u') {
+            output.push(ch);
+            if let Some(next) = chars.next() {
+                output.push(next);
+            }
+            continue;
+        }
+        chars.next();
+
+        // Get hex digits between braces
+        let mut escape = String::from(r"\u");
+        let mut hex = String::new();
+        let mut closed = false;
+        if chars.peek() == Some(&'{') {
+            escape.push('{');
+            chars.next();
+
+            for next in chars.by_ref() {
+                escape.push(next);
+                if next == '}' {
+                    closed = true;
+                    break;
+                }
+                hex.push(next);
+            }
+        }
+
+        // Convert to character
+        let Some(decoded) = closed
+            .then(|| u32::from_str_radix(&hex, 16).ok())
+            .flatten()
+            .and_then(char::from_u32)
+        else {
+            return Err(Error::InvalidUnicodeEscape { escape, line });
+        };
+
+        if is_regex && REGEX_SYNTAX.contains(decoded) {
+            output.push('\\');
+        }
+        output.push(decoded);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_unicode_works() {
+        assert_eq!(unescape_unicode("abc", 0, true).unwrap(), "abc");
+        assert_eq!(
+            unescape_unicode(r"a\u{0303}", 0, true).unwrap(),
+            "a\u{0303}"
+        );
+        assert_eq!(unescape_unicode(r"\u{61}\u{2E}", 0, false).unwrap(), "a.");
+        assert_eq!(unescape_unicode(r"\u{61}\u{2E}", 0, true).unwrap(), r"a\.");
+        assert_eq!(
+            unescape_unicode(r"\w\\u{61}", 0, true).unwrap(),
+            r"\w\\u{61}"
+        );
+
+        assert!(matches!(
+            unescape_unicode(r"\u0303", 0, true),
+            Err(Error::InvalidUnicodeEscape { .. })
+        ));
+        assert!(matches!(
+            unescape_unicode(r"\u{0303", 0, true),
+            Err(Error::InvalidUnicodeEscape { .. })
+        ));
+        assert!(matches!(
+            unescape_unicode(r"\u{xyz}", 0, true),
+            Err(Error::InvalidUnicodeEscape { .. })
+        ));
+        assert!(matches!(
+            unescape_unicode(r"\u{D800}", 0, true),
+            Err(Error::InvalidUnicodeEscape { .. })
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::types::{Error, Phoneme};
+
+/// Parse list of features in square brackets (Eg. `[+voice, -stop]`)
+///
+/// Returns `None` if not a valid list of features
+pub fn parse_features(s: &str) -> Option<Vec<(String, bool)>> {
+    let inner = s.trim().strip_prefix('[')?.strip_suffix(']')?;
+
+    inner
+        .split(',')
+        .map(|feature| {
+            let feature = feature.trim();
+            let mut chars = feature.chars();
+
+            let value = match chars.next()? {
+                '+' => true,
+                '-' => false,
+                _ => return None,
+            };
+
+            let name = chars.as_str().trim();
+            if name.is_empty() || !name.chars().all(|ch| ch.is_alphanumeric() || ch == '_') {
+                return None;
+            }
+
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Expand feature expression to pattern which matches every phoneme with those features
+///
+/// Single-character phonemes are expanded to a character set, otherwise an alternation is used
+pub fn expand_features(
+    name: &str,
+    features: &[(String, bool)],
+    phonemes: &[Phoneme],
+    line: usize,
+) -> Result<String, Error> {
+    // Check that each feature is used by some phoneme
+    for (feature, _) in features {
+        if !phonemes
+            .iter()
+            .any(|phoneme| phoneme.features.contains_key(feature))
+        {
+            return Err(Error::UnknownFeature {
+                feature: feature.to_string(),
+                line,
+            });
+        }
+    }
+
+    let mut symbols: Vec<&str> = phonemes
+        .iter()
+        .filter(|phoneme| phoneme.matches(features))
+        .map(|phoneme| phoneme.symbol.as_str())
+        .collect();
+
+    if symbols.is_empty() {
+        return Err(Error::NoFeatureMatch {
+            name: name.to_string(),
+            line,
+        });
+    }
+
+    if symbols.iter().all(|symbol| symbol.chars().count() == 1) {
+        return Ok(format!(
+            "[{}]",
+            symbols
+                .iter()
+                .map(|symbol| regex::escape(symbol))
+                .collect::<String>()
+        ));
+    }
+
+    // Longest phonemes first, so they are matched before their prefixes
+    symbols.sort_by_key(|symbol| std::cmp::Reverse(symbol.chars().count()));
+    Ok(symbols
+        .iter()
+        .map(|symbol| regex::escape(symbol))
+        .collect::<Vec<_>>()
+        .join("|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phoneme(symbol: &str, features: &str) -> Phoneme {
+        Phoneme::new(
+            symbol.to_string(),
+            parse_features(features).expect("Invalid features"),
+        )
+    }
+
+    #[test]
+    fn parse_features_works() {
+        assert_eq!(
+            parse_features("[+voice, -stop]"),
+            Some(vec![
+                ("voice".to_string(), true),
+                ("stop".to_string(), false)
+            ])
+        );
+        assert_eq!(
+            parse_features(" [ +high ] "),
+            Some(vec![("high".to_string(), true)])
+        );
+
+        assert_eq!(parse_features("[ptk]"), None);
+        assert_eq!(parse_features("[+]"), None);
+        assert_eq!(parse_features("[+a,]"), None);
+        assert_eq!(parse_features("+voice"), None);
+        assert_eq!(parse_features("[+a-z]"), None);
+    }
+
+    #[test]
+    fn expand_features_works() {
+        let phonemes = [
+            phoneme("p", "[-voice, +stop]"),
+            phoneme("b", "[+voice, +stop]"),
+            phoneme("m", "[+voice, +nasal]"),
+            phoneme("ts", "[-voice, +affricate]"),
+        ];
+        let expand =
+            |features: &str| expand_features("X", &parse_features(features).unwrap(), &phonemes, 0);
+
+        assert_eq!(expand("[+voice]").unwrap(), "[bm]");
+        assert_eq!(expand("[+voice, -nasal]").unwrap(), "[b]");
+        assert_eq!(expand("[-voice]").unwrap(), "ts|p");
+
+        assert!(matches!(
+            expand("[+round]"),
+            Err(Error::UnknownFeature { .. })
+        ));
+        assert!(matches!(
+            expand("[+nasal, +stop]"),
+            Err(Error::NoFeatureMatch { .. })
+        ));
+    }
+}
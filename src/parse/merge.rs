@@ -0,0 +1,129 @@
+use super::{substitute_classes, Mini, Phonet};
+use crate::types::{Error, Limits, TestDefinition};
+
+/// What to do when both schemes of `Phonet::merge` define a class or phoneme with the same name
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep definition of first scheme
+    PreferFirst,
+    /// Keep definition of second scheme, like a class which is defined again in one file
+    #[default]
+    PreferSecond,
+    /// Return an error
+    Deny,
+}
+
+impl Phonet {
+    /// Merge two schemes, with rules and tests of `other` after those of `self`
+    ///
+    /// Classes and phonemes are combined, with `policy` for names defined in both schemes.
+    /// Rules of both schemes use the combined classes. A class which uses another class keeps the value it was parsed with
+    ///
+    /// Line numbers of `other` are moved after the lines of `self`, as if both schemes were one file
+    ///
+    /// Diacritics of both schemes are stripped. Mode of `self` is kept
+    pub fn merge(mut self, other: Phonet, policy: MergePolicy) -> Result<Phonet, Error> {
+        // Classes
+        for (name, value) in other.classes {
+            match (self.classes.contains_key(&name), policy) {
+                (true, MergePolicy::Deny) => return Err(Error::MergeConflict { name }),
+                (true, MergePolicy::PreferFirst) => (),
+                _ => {
+                    self.classes.insert(name, value);
+                }
+            }
+        }
+
+        // Phonemes
+        for phoneme in other.phonemes {
+            let existing = self
+                .phonemes
+                .iter_mut()
+                .find(|existing| existing.symbol == phoneme.symbol);
+
+            match (existing, policy) {
+                (Some(_), MergePolicy::Deny) => {
+                    return Err(Error::MergeConflict {
+                        name: format!("/{}/", phoneme.symbol),
+                    })
+                }
+                (Some(_), MergePolicy::PreferFirst) => (),
+                (Some(existing), MergePolicy::PreferSecond) => *existing = phoneme,
+                (None, _) => self.phonemes.push(phoneme),
+            }
+        }
+
+        // Rules, with reasons, targets of tests, and lines moved after those of `self`
+        let reason_offset = self.reasons.len();
+        let rule_offset = self.rules.len();
+        let line_offset = self.lines;
+
+        self.reasons.extend(other.reasons);
+        self.rules.extend(other.rules.into_iter().map(|mut rule| {
+            rule.reason_ref = rule.reason_ref.map(|reason| reason + reason_offset);
+            rule.line += line_offset;
+            rule
+        }));
+        self.tests.extend(other.tests.into_iter().map(|mut test| {
+            if let TestDefinition::Test { target, line, .. } = &mut test {
+                *target = target.map(|target| target + rule_offset);
+                *line = line.map(|line| line + line_offset);
+            }
+            test
+        }));
+        self.lines += other.lines;
+
+        // Substitute combined classes into every rule
+        // Length of patterns was already checked while parsing
+        let limits = Limits {
+            max_pattern_len: usize::MAX,
+            ..Default::default()
+        };
+        for rule in &mut self.rules {
+            let pattern = substitute_classes(&rule.template, &self.classes, rule.line, &limits)?;
+            rule.set_pattern(pattern);
+        }
+
+        // Diacritics
+        self.diacritics.all |= other.diacritics.all;
+        self.diacritics.strip.extend(other.diacritics.strip);
+        self.diacritics.preserve.extend(other.diacritics.preserve);
+
+        self.warnings
+            .extend(other.warnings.into_iter().map(|mut warning| {
+                warning.offset_lines(line_offset);
+                warning
+            }));
+        self.mini.merge(other.mini, policy);
+
+        Ok(self)
+    }
+}
+
+impl Mini {
+    /// Combine minified data of two schemes
+    ///
+    /// Classes of preferred scheme are last, so they override the others
+    fn merge(&mut self, mut other: Mini, policy: MergePolicy) {
+        self.directives.append(&mut other.directives);
+
+        if policy == MergePolicy::PreferFirst {
+            std::mem::swap(&mut self.classes, &mut other.classes);
+        }
+        self.classes.append(&mut other.classes);
+
+        // Targets of tests moved after rules of `self`
+        let rule_offset = self.rules.len();
+        self.tests_targeted.extend(
+            other
+                .tests_targeted
+                .into_iter()
+                .map(|(rule, test)| (rule + rule_offset, test)),
+        );
+
+        self.rules.append(&mut other.rules);
+        self.tests_pos.append(&mut other.tests_pos);
+        self.tests_neg.append(&mut other.tests_neg);
+        self.tests_labelled.append(&mut other.tests_labelled);
+    }
+}
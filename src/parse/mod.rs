@@ -1,31 +1,60 @@
+/// Replace escape sequences
+mod escape;
+/// Classes from distinctive features of phonemes
+mod features;
+/// Combine two schemes
+mod merge;
 /// Split file into statements
 mod statements;
+/// Desugar shorthand syntax of rules
+mod sugar;
 
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use fancy_regex::Regex;
 use fancy_regex_macro::regex;
 
 use crate::{
     types::{
-        Classes,
+        Classes, Diacritics,
         Error::{self, *},
-        Rule, TestDefinition,
+        Limits, Note, ParseOptions, Phoneme, Rule, RunOptions, TestDefinition, Validator,
+        ValidatorFn, Warning,
     },
     Results,
 };
+use escape::unescape_unicode;
+use features::{expand_features, parse_features};
+pub use merge::MergePolicy;
 use statements::split_statements;
+use sugar::{
+    desugar_position, desugar_repetition, quantified_classes, split_reason, vowel_qualifier,
+};
 
 struct RawRule {
     pub intent: bool,
     pub pattern: String,
+    pub source: String,
     pub reason_ref: Option<usize>,
     pub line: usize,
+    /// Name of custom validator, for rule written as `%name`
+    pub validator: Option<String>,
 }
 
+/// Alias for hashmap of class name, and value with line number of definition
+type RawClasses = HashMap<String, (String, usize)>;
+
+/// Alias for hashmap of class name, and features with line number of definition
+type FeatureClasses = HashMap<String, (Vec<(String, bool)>, usize)>;
+
 /// Holds data for minify
 #[derive(Debug)]
 struct Mini {
+    /// Directives defined
+    directives: Vec<String>,
     /// Classes defined
     classes: Vec<String>,
     /// Rules defined
@@ -34,16 +63,23 @@ struct Mini {
     tests_pos: Vec<String>,
     /// Negative tests defined
     tests_neg: Vec<String>,
+    /// Labelled test statements defined
+    tests_labelled: Vec<String>,
+    /// Targeted test statements defined, with index of their rule
+    tests_targeted: Vec<(usize, String)>,
 }
 
 impl Mini {
     /// Create empty struct
     pub fn new() -> Self {
         Mini {
+            directives: Vec::new(),
             classes: Vec::new(),
             rules: Vec::new(),
             tests_pos: Vec::new(),
             tests_neg: Vec::new(),
+            tests_labelled: Vec::new(),
+            tests_targeted: Vec::new(),
         }
     }
 }
@@ -74,8 +110,16 @@ pub struct Phonet {
     pub reasons: Vec<String>,
     /// Classes
     pub classes: Classes,
+    /// Phonemes with distinctive features, in order of definition
+    pub phonemes: Vec<Phoneme>,
     /// Mode - This is only semantical
     pub mode: Mode,
+    /// Diacritics to remove from test words before matching
+    pub diacritics: Diacritics,
+    /// Warnings found while parsing
+    pub warnings: Vec<Warning>,
+    /// Amount of lines in scheme file
+    lines: usize,
     /// Minified data
     mini: Mini,
 }
@@ -90,27 +134,92 @@ impl Phonet {
     }
 
     /// Parse `Phonet` from string
+    ///
+    /// Regex of every rule is compiled while parsing
     pub fn parse(file: &str) -> Result<Phonet, Error> {
+        Self::parse_with(file, &ParseOptions::default())
+    }
+
+    /// Parse `Phonet` from string, with limits on size of scheme
+    ///
+    /// Use for untrusted schemes. A scheme which exceeds any limit fails with an error, instead of parsing slowly
+    pub fn parse_with_limits(file: &str, limits: Limits) -> Result<Phonet, Error> {
+        Self::parse_with(
+            file,
+            &ParseOptions {
+                limits,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Parse `Phonet` from string, with options
+    pub fn parse_with(file: &str, options: &ParseOptions) -> Result<Phonet, Error> {
+        let limits = &options.limits;
+        if file.len() > limits.max_len {
+            return Err(SchemeTooLong {
+                len: file.len(),
+                max: limits.max_len,
+            });
+        }
+
         // Builders
-        let mut raw_classes: Classes = HashMap::new();
+        let mut raw_classes: RawClasses = HashMap::new();
+        let mut feature_classes: FeatureClasses = HashMap::new();
+        // Phonemes, with line number of definition
+        let mut phonemes: Vec<(Phoneme, usize)> = Vec::new();
         let mut tests: Vec<TestDefinition> = Vec::new();
         let mut rules: Vec<RawRule> = Vec::new();
+        // Position qualifiers which use the vowel class, with line
+        let mut vowel_qualifiers: Vec<(String, usize)> = Vec::new();
 
         let mut reasons = Vec::new();
         let mut reason_ref: Option<usize> = None;
 
+        let mut warnings = Vec::new();
+
         // For minify
         let mut mini = Mini::new();
 
         // Mode
         let mut mode: Option<Mode> = None;
 
+        let mut diacritics = Diacritics::default();
+
         // Split file into statements
-        let statements = split_statements(file);
+        let mut statements = split_statements(file);
+        let file_statements = statements.len();
+
+        // Add classes and rules from options, after statements of file
+        // These are not in the file, so they use line 0
+        for define in &options.defines {
+            statements.push((format!("${}", define.trim().trim_start_matches('$')), 0));
+        }
+        for rule in &options.extra_rules {
+            if !rule.trim().starts_with(['+', '!']) {
+                return Err(InvalidExtraRule {
+                    rule: rule.to_string(),
+                });
+            }
+            statements.push((rule.to_string(), 0));
+        }
 
-        for (statement, line) in statements {
+        for (index, (statement, line)) in statements.into_iter().enumerate() {
             let statement = statement.trim();
 
+            // Reasons of file are not used for rules from options
+            let from_options = index >= file_statements;
+            if index == file_statements {
+                reason_ref = None;
+            }
+
+            if statement.len() > limits.max_statement_len {
+                return Err(StatementTooLong {
+                    max: limits.max_statement_len,
+                    line,
+                });
+            }
+
             // Continue for blank
             if statement.is_empty() {
                 continue;
@@ -146,6 +255,91 @@ impl Phonet {
                         };
                     }
 
+                    // Directive
+                    '%' => {
+                        let rest = chars.as_str().trim();
+                        let (name, value) = rest.split_once(' ').unwrap_or((rest, ""));
+
+                        // Characters given, ignoring spaces
+                        let value = unescape_unicode(&value.replace(' ', ""), line, false)?;
+                        let list: Vec<char> = value.chars().collect();
+
+                        match name {
+                            // Strip every diacritic, if no characters are given
+                            "strip" => {
+                                if list.is_empty() {
+                                    diacritics.all = true;
+                                } else {
+                                    diacritics.strip.extend(list);
+                                }
+                            }
+
+                            "preserve" => {
+                                if list.is_empty() {
+                                    return Err(Error::EmptyDirective { line });
+                                }
+                                diacritics.preserve.extend(list);
+                            }
+
+                            _ => {
+                                return Err(Error::UnknownDirective {
+                                    name: name.to_string(),
+                                    line,
+                                })
+                            }
+                        }
+
+                        // Add directive for minify
+                        mini.directives
+                            .push(format!("%{name} {value}").trim().to_string());
+                    }
+
+                    // Phoneme
+                    '/' => {
+                        let Some((symbol, rest)) = chars.as_str().split_once('/') else {
+                            return Err(Error::InvalidPhoneme { line });
+                        };
+
+                        // Get features, after equals sign
+                        let symbol = unescape_unicode(symbol.trim(), line, false)?;
+                        let features = match rest.trim().strip_prefix('=').and_then(parse_features)
+                        {
+                            Some(features) if !symbol.is_empty() => features,
+                            _ => return Err(Error::InvalidPhoneme { line }),
+                        };
+
+                        // Add phoneme for minify
+                        mini.classes.push(format!(
+                            "/{symbol}/=[{}]",
+                            features
+                                .iter()
+                                .map(|(name, value)| format!(
+                                    "{}{name}",
+                                    if *value { '+' } else { '-' }
+                                ))
+                                .collect::<Vec<_>>()
+                                .join(",")
+                        ));
+
+                        let phoneme = Phoneme::new(symbol, features);
+
+                        // Warn if phoneme already exists - New definition overrides the old one
+                        match phonemes
+                            .iter_mut()
+                            .find(|(old, _)| old.symbol == phoneme.symbol)
+                        {
+                            Some(old) => {
+                                warnings.push(Warning::PhonemeRedefined {
+                                    symbol: phoneme.symbol.clone(),
+                                    first_line: old.1,
+                                    line,
+                                });
+                                *old = (phoneme, line);
+                            }
+                            None => phonemes.push((phoneme, line)),
+                        }
+                    }
+
                     // Class
                     '$' => {
                         let mut split = chars.as_str().split('=');
@@ -157,10 +351,7 @@ impl Phonet {
                         };
 
                         // Check if name is valid
-                        if !regex!(r"^\w+$")
-                            .is_match(&name)
-                            .expect("Failed checking regex match. This error should NEVER APPEAR!")
-                        {
+                        if !regex!(r"^\w+$").is_match(&name).unwrap_or(false) {
                             return Err(Error::InvalidClassName { name, line });
                         }
 
@@ -170,9 +361,29 @@ impl Phonet {
                             None => return Err(Error::NoClassValue { name, line }),
                         };
 
-                        // Check that class does not already exist
-                        if raw_classes.get(&name).is_some() {
-                            return Err(Error::ClassAlreadyExist { name, line });
+                        // Warn if class already exists - New definition overrides the old one
+                        // Classes from options are meant to override, so are not warned
+                        if let Some((_, first_line)) =
+                            raw_classes.get(&name).filter(|_| !from_options)
+                        {
+                            warnings.push(Warning::ClassRedefined {
+                                name: name.to_string(),
+                                first_line: *first_line,
+                                line,
+                            });
+                        }
+
+                        // Replace unicode escapes
+                        let pattern = unescape_unicode(&value.replace(' ', ""), line, true)?;
+
+                        // Features are expanded after all phonemes are defined
+                        // Otherwise, check that value is valid regex by itself
+                        feature_classes.remove(&name);
+                        match parse_features(value) {
+                            Some(features) => {
+                                feature_classes.insert(name.to_string(), (features, line));
+                            }
+                            None => validate_class(&name, &pattern, line)?,
                         }
 
                         // Add raw line
@@ -186,8 +397,7 @@ impl Phonet {
                         // Wrap value in NON-CAPTURING GROUP (just in case)
                         // This is non-capturing, for classes to work with back-references
                         // otherwise classes would be inherently capturing, and count towards group index in back-reference
-                        raw_classes
-                            .insert(name.to_string(), format!("(?:{})", value.replace(' ', "")));
+                        raw_classes.insert(name.to_string(), (format!("(?:{pattern})"), line));
                     }
 
                     // Rule
@@ -195,18 +405,53 @@ impl Phonet {
                         // `+` for true, `!` for false
                         let intent = first != '!';
 
-                        let pattern = chars.as_str().replace(' ', "");
+                        // Split inline reason, which is only used for this rule
+                        let (pattern, inline_reason) = split_reason(chars.as_str());
+                        let reason_ref = match inline_reason {
+                            Some(reason) if !reason.is_empty() => {
+                                reasons.push(reason.to_string());
+                                Some(reasons.len() - 1)
+                            }
+                            _ => reason_ref,
+                        };
+
+                        // Pattern as written, for errors and display
+                        let source = pattern.trim().to_string();
+
+                        let pattern = pattern.replace(' ', "");
 
                         // Add rule for minify
                         mini.rules
                             .push(first.to_string() + &pattern.replace('⟨', "<").replace('⟩', ">"));
 
+                        // Vowel class is checked after every class is defined
+                        if let Some(qualifier) = vowel_qualifier(&pattern) {
+                            vowel_qualifiers.push((qualifier.to_string(), line));
+                        }
+
+                        // Custom validator, registered with library
+                        let validator = pattern
+                            .strip_prefix('%')
+                            .filter(|name| regex!(r"^\w+$").is_match(name).unwrap_or(false))
+                            .map(str::to_string);
+
+                        // Convert optional classes, and check repetitions of classes
+                        let pattern = desugar_repetition(&pattern, line)?;
+
+                        // Convert position qualifier and environment shorthand to look-arounds
+                        let pattern = desugar_position(&pattern, line)?;
+
+                        // Replace unicode escapes
+                        let pattern = unescape_unicode(&pattern, line, true)?;
+
                         // Add rule
                         rules.push(RawRule {
                             intent,
                             pattern,
+                            source,
                             reason_ref,
                             line,
+                            validator,
                         });
                     }
 
@@ -217,6 +462,17 @@ impl Phonet {
                             chars.next();
                         }
 
+                        // Only test with rule above, if `^` is given
+                        let target = if chars.as_str().starts_with('^') {
+                            chars.next();
+                            match rules.len().checked_sub(1) {
+                                Some(rule) => Some(rule),
+                                None => return Err(Error::NoTargetRule { line }),
+                            }
+                        } else {
+                            None
+                        };
+
                         // Check intent
                         // `+` for true, `!` for false
                         let intent = match chars.next() {
@@ -233,21 +489,71 @@ impl Phonet {
                             None => continue,
                         };
 
+                        // Get label in square brackets, if any
+                        let rest = chars.as_str().trim_start();
+                        let (label, rest) = match rest.strip_prefix('[') {
+                            Some(rest) => match rest.split_once(']') {
+                                Some((label, rest)) => (Some(label.trim().to_string()), rest),
+                                None => return Err(Error::UnclosedTestLabel { line }),
+                            },
+                            None => (None, rest),
+                        };
+
+                        // Add targeted or labelled test statement for minify
+                        // Targeted tests are kept with their rule
+                        // Unlabelled targeted tests have a statement for each word, to be deduplicated
+                        if target.is_some() || label.is_some() {
+                            let words: Vec<_> = rest.split_whitespace().collect();
+                            let statements = match (&label, target) {
+                                (None, Some(_)) => {
+                                    words.iter().map(|word| word.to_string()).collect()
+                                }
+                                _ => vec![words.join(" ")],
+                            };
+
+                            for words in statements {
+                                let statement = format!(
+                                    "?{}{}{}{}",
+                                    if target.is_some() { "^" } else { "" },
+                                    if intent { '+' } else { '!' },
+                                    label
+                                        .as_ref()
+                                        .map(|label| format!("[{label}]"))
+                                        .unwrap_or_default(),
+                                    words,
+                                );
+
+                                match target {
+                                    Some(rule) => mini.tests_targeted.push((rule, statement)),
+                                    None => mini.tests_labelled.push(statement),
+                                }
+                            }
+                        }
+
                         // Split at space
-                        let words = chars.as_str().split_whitespace();
+                        let words = rest.split_whitespace();
                         for word in words {
                             let word = word.trim().to_string();
 
                             // Add test for minify
-                            if intent {
-                                mini.tests_pos.push(word.clone());
-                            } else {
-                                mini.tests_neg.push(word.clone());
+                            if label.is_none() && target.is_none() {
+                                if intent {
+                                    mini.tests_pos.push(word.clone());
+                                } else {
+                                    mini.tests_neg.push(word.clone());
+                                }
                             }
 
                             // Add test
                             if !word.is_empty() {
-                                tests.push(TestDefinition::Test { intent, word });
+                                let word = unescape_unicode(&word, line, false)?;
+                                tests.push(TestDefinition::Test {
+                                    intent,
+                                    word,
+                                    label: label.clone(),
+                                    target,
+                                    line: Some(line),
+                                });
                             }
                         }
                     }
@@ -262,7 +568,7 @@ impl Phonet {
                         // Reason note
                         if chars.as_str().starts_with('*') {
                             chars.next();
-                            tests.push(TestDefinition::Note(chars.as_str().trim().to_string()));
+                            tests.push(TestDefinition::Note(Note::new(chars.as_str())));
                         }
 
                         // Add reason
@@ -272,9 +578,9 @@ impl Phonet {
 
                     // Note
                     '*' => {
-                        let msg = chars.as_str().trim().to_string();
-                        if !msg.is_empty() {
-                            tests.push(TestDefinition::Note(msg));
+                        let note = Note::new(chars.as_str());
+                        if !note.text.is_empty() {
+                            tests.push(TestDefinition::Note(note));
                         }
                     }
 
@@ -284,88 +590,492 @@ impl Phonet {
             }
         }
 
-        //TODO Add line number
+        // Expand classes of features, if any phonemes are defined
+        // Otherwise, they are used as regex character sets
+        let phonemes: Vec<Phoneme> = phonemes.into_iter().map(|(phoneme, _)| phoneme).collect();
+        if !phonemes.is_empty() {
+            let mut feature_classes: Vec<_> = feature_classes.into_iter().collect();
+            feature_classes.sort_by_key(|(_, (_, line))| *line);
+
+            for (name, (features, line)) in feature_classes {
+                let pattern = expand_features(&name, &features, &phonemes, line)?;
+                raw_classes.insert(name, (format!("(?:{pattern})"), line));
+            }
+        }
+
+        // Position qualifier uses vowel class, which user may not have written
+        if !raw_classes.contains_key("V") {
+            if let Some((qualifier, line)) = vowel_qualifiers.into_iter().next() {
+                return Err(Error::QualifierWithoutVowels { qualifier, line });
+            }
+        }
+
+        // Substitute classes into other classes
+        let values: Classes = raw_classes
+            .iter()
+            .map(|(name, (value, _))| (name.to_string(), value.to_string()))
+            .collect();
         let mut classes = Classes::new();
-        for (name, value) in &raw_classes {
+        for (name, (value, line)) in &raw_classes {
             classes.insert(
                 name.to_string(),
-                substitute_classes(value, &raw_classes, 0)?,
+                substitute_classes(value, &values, *line, limits)?,
             );
         }
         let classes = classes;
 
+        // Warn for classes used as literal text in rules
+        warnings.append(&mut find_shadowed_classes(&raw_classes, &rules));
+
+        // Warn for tests which are defined more than once
+        warnings.append(&mut find_duplicate_tests(&tests));
+
         // Convert rules to regex rules
-        let rules = make_regex(rules, &classes)?;
+        let mut rules = make_regex(rules, &classes, &values, limits)?;
+        for rule in &mut rules {
+            rule.backtrack_limit = options.backtrack_limit;
+        }
 
         // Use default mode if not given
         let mode = mode.unwrap_or_default();
 
-        Ok(Phonet {
+        let mut scheme = Phonet {
             rules,
             tests,
             reasons,
             classes,
+            phonemes,
             mode,
+            diacritics,
+            warnings,
+            lines: file.lines().count(),
             mini,
+        };
+
+        // Compile now, unless lazy
+        if !options.lazy {
+            scheme.compile_all()?;
+        }
+
+        // Warn for rules which never change a result
+        // Shadowed rules are only found with compiled rules
+        let mut redundant = find_redundant_rules(&scheme.rules, !options.lazy);
+        scheme.warnings.append(&mut redundant);
+
+        Ok(scheme)
+    }
+
+    /// Compile regex of every rule which is not yet compiled
+    ///
+    /// Returns error for first invalid rule
+    pub fn compile_all(&self) -> Result<(), Error> {
+        for rule in &self.rules {
+            rule.regex()?;
+        }
+        Ok(())
+    }
+
+    /// Use function to check words, for every rule written as `%name` with this name
+    ///
+    /// Function returns `true` if word matches, like the pattern of a rule. So `+ %name` needs the function to return `true`, and `! %name` needs it to return `false`
+    ///
+    /// Returns amount of rules which use validator
+    pub fn register_validator<F>(&mut self, name: &str, function: F) -> usize
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        let function: ValidatorFn = Arc::new(function);
+        let mut count = 0;
+
+        for rule in &mut self.rules {
+            if let Some(validator) = &mut rule.validator {
+                if validator.name == name {
+                    validator.function = Some(function.clone());
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Remove tests with the same intent, word, and target rule as an earlier test
+    ///
+    /// Warnings for duplicate tests are removed. Returns amount of tests removed
+    pub fn dedup_tests(&mut self) -> usize {
+        let count = self.tests.len();
+
+        let mut seen = HashSet::new();
+        self.tests.retain(|test| match test {
+            TestDefinition::Test {
+                intent,
+                word,
+                target,
+                ..
+            } => seen.insert((*intent, word.clone(), *target)),
+            TestDefinition::Note(_) => true,
+        });
+
+        // Minified tests
+        let mut seen = HashSet::new();
+        self.mini.tests_pos.retain(|word| seen.insert(word.clone()));
+        let mut seen = HashSet::new();
+        self.mini.tests_neg.retain(|word| seen.insert(word.clone()));
+        let mut seen = HashSet::new();
+        self.mini
+            .tests_targeted
+            .retain(|test| seen.insert(test.clone()));
+
+        self.warnings
+            .retain(|warning| !matches!(warning, Warning::DuplicateTest { .. }));
+
+        count - self.tests.len()
+    }
+
+    /// Return error for first warning found while parsing, if any
+    ///
+    /// Use to treat warnings as errors
+    pub fn deny_warnings(mut self) -> Result<Self, Error> {
+        if self.warnings.is_empty() {
+            return Ok(self);
+        }
+
+        Err(DeniedWarning {
+            warning: self.warnings.remove(0),
         })
     }
 
     /// Minify Phonet scheme as string
     pub fn minify(&self, do_tests: bool) -> String {
         let s = ';';
-        let c = self.mini.classes.join(";");
-        let r = self.mini.rules.join(";");
+        let c = self
+            .mini
+            .directives
+            .iter()
+            .chain(&self.mini.classes)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(";");
 
         if do_tests {
+            // Targeted tests must stay after their rule
+            let r = self
+                .mini
+                .rules
+                .iter()
+                .enumerate()
+                .flat_map(|(index, rule)| {
+                    std::iter::once(rule).chain(
+                        self.mini
+                            .tests_targeted
+                            .iter()
+                            .filter(move |(target, _)| *target == index)
+                            .map(|(_, test)| test),
+                    )
+                })
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(";");
+
             // Include tests
-            format!(
+            let mut output = format!(
                 "{c}{s}{r}{s}?+{tp}{s}?!{tn}",
                 tp = self.mini.tests_pos.join(" "),
                 tn = self.mini.tests_neg.join(" "),
-            )
+            );
+
+            // Labelled tests are separate statements
+            for test in &self.mini.tests_labelled {
+                output.push(s);
+                output.push_str(test);
+            }
+
+            output
         } else {
             // Don't include tests
-            format!("{c}{s}{r}")
+            format!("{c}{s}{r}", r = self.mini.rules.join(";"))
         }
     }
 
+    /// Remove diacritics from word, as set with `%` directives
+    pub fn strip_diacritics(&self, word: &str) -> String {
+        self.diacritics.apply(word)
+    }
+
     /// Run tests, return results
     pub fn run(&self) -> Results {
         Results::run(self)
     }
+
+    /// Run tests on multiple threads, return results
+    ///
+    /// Results are always in order of definition, regardless of amount of jobs
+    pub fn run_jobs(&self, jobs: usize) -> Results {
+        Results::run_jobs(self, jobs)
+    }
+
+    /// Run tests with options, return results
+    pub fn run_with(&self, options: &RunOptions) -> Results {
+        Results::run_with(self, options)
+    }
 }
 
-/// Substitute classes in rule and create regex
-fn make_regex(raw_rules: Vec<RawRule>, classes: &Classes) -> Result<Vec<Rule>, Error> {
+/// Substitute classes in rule, to create regex rule
+///
+/// `values` are classes before substitution, to find classes used by each rule
+///
+/// Regex is not compiled until used
+fn make_regex(
+    raw_rules: Vec<RawRule>,
+    classes: &Classes,
+    values: &Classes,
+    limits: &Limits,
+) -> Result<Vec<Rule>, Error> {
     let mut rules: Vec<Rule> = Vec::new();
 
     for RawRule {
         intent,
         pattern,
+        source,
         reason_ref,
         line,
+        validator,
     } in raw_rules
     {
-        let pat = substitute_classes(&pattern, classes, line)?;
-        let pattern = match Regex::new(&pat) {
-            Ok(x) => x,
-            Err(err) => return Err(RegexFail { err, line }),
-        };
+        let substituted = substitute_classes(&pattern, classes, line, limits)?;
 
-        rules.push(Rule {
-            intent,
-            pattern,
-            reason_ref,
+        // Optional and repeated classes must match whole phonemes, not nothing
+        for name in quantified_classes(&pattern) {
+            let Some(value) = classes.get(&name) else {
+                continue;
+            };
+            let matches_empty = Regex::new(&format!("^{value}$"))
+                .and_then(|regex| regex.is_match(""))
+                .unwrap_or(false);
+            if matches_empty {
+                return Err(Error::EmptyQuantifiedClass { name, line });
+            }
+        }
+
+        let mut rule = Rule::new(intent, substituted, reason_ref, line);
+        rule.source = source;
+        rule.classes = used_classes(&pattern, values);
+        rule.template = pattern;
+        rule.validator = validator.map(|name| Validator {
+            name,
+            function: None,
         });
+        rules.push(rule);
     }
 
     Ok(rules)
 }
 
+/// Get names of classes used in pattern, including classes used by those classes
+///
+/// Sorted by name, without duplicates
+fn used_classes(pattern: &str, values: &Classes) -> Vec<String> {
+    let mut used = Vec::new();
+    let mut queue = vec![pattern.to_string()];
+
+    while let Some(pattern) = queue.pop() {
+        for name in regex!(r"⟨([^⟩]*)⟩")
+            .captures_iter(&replace_angle_brackets(&pattern))
+            .filter_map(|captures| Some(captures.ok()?.get(1)?.as_str().to_string()))
+        {
+            if used.contains(&name) {
+                continue;
+            }
+            if let Some(value) = values.get(&name) {
+                queue.push(value.to_string());
+            }
+            used.push(name);
+        }
+    }
+
+    used.sort();
+    used
+}
+
+/// Find classes which have names that are used as literal text in rules
+///
+/// Eg. Rule `^CV$` probably should be `^<C><V>$`
+fn find_shadowed_classes(raw_classes: &RawClasses, rules: &[RawRule]) -> Vec<Warning> {
+    // Sort classes by definition, for consistent order
+    let mut classes: Vec<(&String, usize)> = raw_classes
+        .iter()
+        .filter(|(name, _)| name.chars().all(char::is_alphanumeric))
+        .map(|(name, (_, line))| (name, *line))
+        .collect();
+    classes.sort_by_key(|(_, line)| *line);
+
+    let mut warnings = Vec::new();
+
+    for rule in rules {
+        let literal = literal_text(&rule.pattern);
+
+        for (name, class_line) in &classes {
+            if literal.contains(name.as_str()) {
+                warnings.push(Warning::ClassShadowsLiteral {
+                    name: name.to_string(),
+                    class_line: *class_line,
+                    line: rule.line,
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Find rules which never change a result
+///
+/// - Rule with the same intent and pattern as an earlier rule
+/// - Negative rule with literal pattern, which is matched by an earlier negative rule (if `find_shadowed` is true)
+///
+/// Rules with a custom validator do not shadow, and are not shadowed by, other rules
+///
+/// Eg. `! aa` makes a later `! baa` redundant
+fn find_redundant_rules(rules: &[Rule], find_shadowed: bool) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    for (index, rule) in rules.iter().enumerate() {
+        let earlier = &rules[..index];
+
+        // Same rule
+        if let Some(first) = earlier
+            .iter()
+            .find(|first| first.intent == rule.intent && first.pattern == rule.pattern)
+        {
+            warnings.push(Warning::DuplicateRule {
+                first_line: first.line,
+                line: rule.line,
+            });
+            continue;
+        }
+
+        // Literal negative rule, matched by earlier negative rule
+        // Pattern of validator is name, not text of word
+        if !find_shadowed || rule.intent || rule.validator.is_some() || !is_literal(&rule.pattern) {
+            continue;
+        }
+        if let Some(first) = earlier.iter().find(|first| {
+            !first.intent
+                && first.validator.is_none()
+                && !has_assertions(&first.pattern)
+                && first
+                    .regex()
+                    .is_ok_and(|regex| regex.is_match(&rule.pattern).unwrap_or(false))
+        }) {
+            warnings.push(Warning::ShadowedRule {
+                first_line: first.line,
+                line: rule.line,
+            });
+        }
+    }
+
+    warnings
+}
+
+/// Returns `true` if pattern has no regex syntax, and only matches itself
+fn is_literal(pattern: &str) -> bool {
+    !pattern.is_empty() && !pattern.contains(|ch| r"\.+*?()|[]{}^$".contains(ch))
+}
+
+/// Returns `true` if pattern may depend on text around a match
+///
+/// Eg. Anchors, look-arounds, and escapes (such as word boundaries)
+fn has_assertions(pattern: &str) -> bool {
+    pattern.contains(['^', '$', '\\'])
+        || pattern.contains("(?=")
+        || pattern.contains("(?!")
+        || pattern.contains("(?<")
+}
+
+/// Find tests with the same intent and word as an earlier test
+fn find_duplicate_tests(tests: &[TestDefinition]) -> Vec<Warning> {
+    // Line of first definition of each test
+    let mut first_lines: HashMap<(bool, &str, Option<usize>), usize> = HashMap::new();
+    let mut warnings = Vec::new();
+
+    for test in tests {
+        let TestDefinition::Test {
+            intent,
+            word,
+            target,
+            line: Some(line),
+            ..
+        } = test
+        else {
+            continue;
+        };
+
+        match first_lines.get(&(*intent, word.as_str(), *target)) {
+            Some(first_line) => warnings.push(Warning::DuplicateTest {
+                test: format!(
+                    "?{}{} {word}",
+                    if target.is_some() { "^" } else { "" },
+                    if *intent { '+' } else { '!' }
+                ),
+                first_line: *first_line,
+                line: *line,
+            }),
+
+            None => {
+                first_lines.insert((*intent, word, *target), *line);
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Get literal text of pattern, without class names, escapes, or group names
+fn literal_text(pattern: &str) -> String {
+    let pattern = replace_angle_brackets(pattern);
+
+    regex!(r"⟨[^⟩]*⟩|\\k<[^>]*>|\(\?P?<[^>=!]*>|\\.")
+        .replace_all(&pattern, " ")
+        .to_string()
+}
+
+/// Check that class value compiles as a regex fragment by itself
+///
+/// References to other classes are replaced with empty groups, as they might not be defined yet
+fn validate_class(name: &str, value: &str, line: usize) -> Result<(), Error> {
+    let value = replace_angle_brackets(value);
+    let fragment = regex!(r"⟨[^⟩]*⟩").replace_all(&value, "(?:)");
+
+    match Regex::new(&fragment) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ClassRegexFail {
+            name: name.to_string(),
+            err,
+            line,
+        }),
+    }
+}
+
 /// Substitute class names regex rule with class values (recursively)
 ///
 /// `pattern` argument must not contain spaces
-fn substitute_classes(pattern: &str, classes: &Classes, line: usize) -> Result<String, Error> {
+pub(crate) fn substitute_classes(
+    pattern: &str,
+    classes: &Classes,
+    line: usize,
+    limits: &Limits,
+) -> Result<String, Error> {
+    substitute_classes_nested(pattern, classes, line, limits, 0)
+}
+
+/// Substitute class names, inside classes which are `depth` deep
+fn substitute_classes_nested(
+    pattern: &str,
+    classes: &Classes,
+    line: usize,
+    limits: &Limits,
+    depth: usize,
+) -> Result<String, Error> {
     let mut output = String::new();
 
     // Build class name
@@ -415,8 +1125,28 @@ fn substitute_classes(pattern: &str, classes: &Classes, line: usize) -> Result<S
             });
         };
 
+                if depth >= limits.max_class_depth {
+                    return Err(Error::ClassTooDeep {
+                        name,
+                        max: limits.max_class_depth,
+                        line,
+                    });
+                }
+
                 // Add value to output (recursively)
-                output.push_str(&substitute_classes(value, classes, line)?);
+                output.push_str(&substitute_classes_nested(
+                    value,
+                    classes,
+                    line,
+                    limits,
+                    depth + 1,
+                )?);
+                if output.len() > limits.max_pattern_len {
+                    return Err(Error::PatternTooLong {
+                        max: limits.max_pattern_len,
+                        line,
+                    });
+                }
                 // Finish building name
                 name_build = None;
             }
@@ -442,6 +1172,33 @@ fn substitute_classes(pattern: &str, classes: &Classes, line: usize) -> Result<S
     Ok(output)
 }
 
+/// Substitute class names in rule with class values, each in a named capture group
+///
+/// Group of each class is named `phonet_class_0`, `phonet_class_1`, and so on. Returns pattern, and name of class of each group
+///
+/// Classes used by those classes are not captured. Returns `None` if a class is not found
+pub(crate) fn capture_classes(pattern: &str, classes: &Classes) -> Option<(String, Vec<String>)> {
+    let pattern = replace_angle_brackets(pattern);
+    let mut output = String::new();
+    let mut names = Vec::new();
+
+    let mut rest = pattern.as_str();
+    while let Some(start) = rest.find('⟨') {
+        let end = start + rest[start..].find('⟩')?;
+        let name = &rest[start + '⟨'.len_utf8()..end];
+        let value = classes.get(name)?;
+
+        output.push_str(&rest[..start]);
+        output.push_str(&format!("(?P<phonet_class_{}>{value})", names.len()));
+        names.push(name.to_string());
+
+        rest = &rest[end + '⟩'.len_utf8()..];
+    }
+    output.push_str(rest);
+
+    Some((output, names))
+}
+
 /// Replace ascii `<` and `>` with `⟨` and `⟩` respectively, for classes
 ///
 /// Does not replace `<` and `>` with use in look-behinds or named group definitions or references
@@ -473,6 +1230,63 @@ mod tests {
         assert_eq!(replace_angle_brackets("<abc>>"), "⟨abc⟩>");
     }
 
+    #[test]
+    fn capture_classes_works() {
+        let classes = Classes::from([
+            ("C".to_string(), "(?:[ptk])".to_string()),
+            ("V".to_string(), "(?:[aio])".to_string()),
+        ]);
+
+        assert_eq!(
+            capture_classes("^<C><V>$", &classes),
+            Some((
+                "^(?P<phonet_class_0>(?:[ptk]))(?P<phonet_class_1>(?:[aio]))$".to_string(),
+                vec!["C".to_string(), "V".to_string()]
+            ))
+        );
+        assert_eq!(
+            capture_classes("(?<=a)b", &classes),
+            Some(("(?<=a)b".to_string(), vec![]))
+        );
+        assert_eq!(capture_classes("<X>", &classes), None);
+    }
+
+    #[test]
+    fn literal_text_works() {
+        assert_eq!(literal_text("^<C><V>$"), "^  $");
+        assert_eq!(literal_text("^CV$"), "^CV$");
+        assert_eq!(literal_text(r"\w(?<x>.)\k<x>"), "  .) ");
+    }
+
+    #[test]
+    fn validate_class_works() {
+        assert!(validate_class("C", "[ptk]", 0).is_ok());
+        assert!(validate_class("_", "[<C><V>]", 0).is_ok());
+        assert!(validate_class("C", "<V>|ts", 0).is_ok());
+
+        assert!(matches!(
+            validate_class("C", "[ptk", 0),
+            Err(Error::ClassRegexFail { .. })
+        ));
+        assert!(matches!(
+            validate_class("C", "<V>)", 0),
+            Err(Error::ClassRegexFail { .. })
+        ));
+    }
+
+    #[test]
+    fn used_classes_works() {
+        let values = Classes::from([
+            ("C".to_string(), "[ptk]".to_string()),
+            ("V".to_string(), "[aio]".to_string()),
+            ("S".to_string(), "<C><V>".to_string()),
+        ]);
+
+        assert_eq!(used_classes("^<S>+$", &values), ["C", "S", "V"]);
+        assert_eq!(used_classes("<V><V>", &values), ["V"]);
+        assert!(used_classes("(?<=a)b", &values).is_empty());
+    }
+
     #[test]
     fn substitute_classes_works() {
         let classes = Classes::from([
@@ -480,43 +1294,75 @@ mod tests {
             ("Vowels".to_string(), "[aio]".to_string()),
             ("_".to_string(), "[<C><Vowels>]".to_string()),
         ]);
+        let limits = Limits::default();
 
         assert_eq!(
-            substitute_classes("<C>", &classes, 0).unwrap(),
+            substitute_classes("<C>", &classes, 0, &limits).unwrap(),
             "[ptk]".to_string()
         );
 
         assert_eq!(
-            substitute_classes("<C>-<Vowels>", &classes, 0).unwrap(),
+            substitute_classes("<C>-<Vowels>", &classes, 0, &limits).unwrap(),
             "[ptk]-[aio]".to_string()
         );
 
         assert_eq!(
-            substitute_classes("<_>", &classes, 0).unwrap(),
+            substitute_classes("<_>", &classes, 0, &limits).unwrap(),
             "[[ptk][aio]]".to_string()
         );
 
         assert_eq!(
-            substitute_classes("(?<=1)", &classes, 0).unwrap(),
+            substitute_classes("(?<=1)", &classes, 0, &limits).unwrap(),
             "(?<=1)".to_string()
         );
 
         assert_eq!(
-            substitute_classes("(?<abc><C>)", &classes, 0).unwrap(),
+            substitute_classes("(?<abc><C>)", &classes, 0, &limits).unwrap(),
             "(?<abc>[ptk])".to_string()
         );
 
-        assert_eq!(substitute_classes("a>b", &classes, 0).unwrap(), "a>b");
-        assert_eq!(substitute_classes("a<b", &classes, 0).unwrap(), "a<b");
+        assert_eq!(
+            substitute_classes("a>b", &classes, 0, &limits).unwrap(),
+            "a>b"
+        );
+        assert_eq!(
+            substitute_classes("a<b", &classes, 0, &limits).unwrap(),
+            "a<b"
+        );
 
-        assert!(match substitute_classes("<c>", &classes, 0) {
+        assert!(match substitute_classes("<c>", &classes, 0, &limits) {
             Err(Error::ClassNotFound { .. }) => true,
             _ => false,
         });
 
-        assert!(match substitute_classes("<a<b>c>", &classes, 0) {
+        assert!(match substitute_classes("<a<b>c>", &classes, 0, &limits) {
             Err(Error::ClassNotFound { .. }) => true,
             _ => false,
         });
+
+        // Classes which use each other in a loop
+        let classes = Classes::from([
+            ("A".to_string(), "a<B>".to_string()),
+            ("B".to_string(), "b<A>".to_string()),
+        ]);
+        assert!(matches!(
+            substitute_classes("<A>", &classes, 0, &limits),
+            Err(Error::ClassTooDeep { .. })
+        ));
+
+        // Length doubles with each class
+        let classes = Classes::from([
+            ("A".to_string(), "ab".to_string()),
+            ("B".to_string(), "<A><A>".to_string()),
+            ("C".to_string(), "<B><B>".to_string()),
+        ]);
+        let limits = Limits {
+            max_pattern_len: 6,
+            ..Default::default()
+        };
+        assert!(matches!(
+            substitute_classes("<C>", &classes, 0, &limits),
+            Err(Error::PatternTooLong { .. })
+        ));
     }
 }
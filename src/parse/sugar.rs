@@ -0,0 +1,436 @@
+use fancy_regex_macro::regex;
+
+use crate::types::Error;
+
+/// Desugar position qualifier of rule pattern into anchors and look-arounds, then desugar environment
+///
+/// - `initial:X` matches `X` only at start of word
+/// - `medial:X` matches `X` only with some letter before and after it
+/// - `final:X` matches `X` only at end of word
+/// - `onset:X` matches `X` only directly before a vowel (class `V`)
+/// - `coda:X` matches `X` only directly after a vowel, and not before a vowel (class `V`)
+///
+/// Patterns without a qualifier are only desugared for environment
+///
+/// `pattern` argument must not contain spaces
+pub fn desugar_position(pattern: &str, line: usize) -> Result<String, Error> {
+    let Some((qualifier, rest)) = pattern.split_once(':') else {
+        return desugar_environment(pattern, line);
+    };
+
+    let (before, after) = match qualifier {
+        "initial" => ("^", ""),
+        "medial" => ("(?<=.)", "(?=.)"),
+        "final" => ("", "$"),
+        "onset" => ("", "(?=⟨V⟩)"),
+        "coda" => ("(?<=⟨V⟩)", "(?!⟨V⟩)"),
+
+        // Not a qualifier
+        _ => return desugar_environment(pattern, line),
+    };
+
+    Ok(format!(
+        "{before}(?:{}){after}",
+        desugar_environment(rest, line)?
+    ))
+}
+
+/// Get position qualifier of rule pattern, if it uses the vowel class (`V`)
+///
+/// `pattern` argument must not contain spaces
+pub fn vowel_qualifier(pattern: &str) -> Option<&str> {
+    pattern
+        .split_once(':')
+        .map(|(qualifier, _)| qualifier)
+        .filter(|qualifier| matches!(*qualifier, "onset" | "coda"))
+}
+
+/// Desugar environment shorthand of rule pattern into look-arounds
+///
+/// `X / L_R` matches `X` only after `L`, and only before `R`. Either side of the `_` may be empty.
+///
+/// A side starting with `!` is negated: `X / _!R` matches `X` only when *not* before `R`
+///
+/// Patterns without a slash are returned as-is. Use `\/` for a literal slash
+///
+/// `pattern` argument must not contain spaces
+pub fn desugar_environment(pattern: &str, line: usize) -> Result<String, Error> {
+    // Split into target and environment
+    let (target, environment) = match split_top_level(pattern, '/').as_slice() {
+        // No environment
+        [_] => return Ok(unescape_slash(pattern)),
+
+        [target, environment] => (target.to_string(), environment.to_string()),
+
+        _ => {
+            return Err(Error::EnvironmentMultipleSlash {
+                pattern: pattern.to_string(),
+                line,
+            })
+        }
+    };
+
+    // Split environment at focus
+    let sides = split_top_level(&environment, '_');
+    let [before, after] = sides.as_slice() else {
+        return Err(Error::EnvironmentFocus {
+            pattern: pattern.to_string(),
+            line,
+        });
+    };
+
+    // Wrap target in non-capturing group, so alternations do not leak into look-arounds
+    Ok(format!(
+        "{}(?:{}){}",
+        lookaround(before, true),
+        unescape_slash(&target),
+        lookaround(after, false),
+    ))
+}
+
+/// Wrap side of environment in look-around, using negative look-around if side starts with `!`
+///
+/// A `#` at the outer edge of side is a word boundary: `#_` is start of word, and `_#` is end of word
+fn lookaround(side: &str, is_before: bool) -> String {
+    if side.is_empty() {
+        return String::new();
+    }
+
+    let (side, negative) = match side.strip_prefix('!') {
+        Some(side) => (side, true),
+        None => (side, false),
+    };
+
+    // Word boundary
+    let side = if is_before {
+        match side.strip_prefix('#') {
+            Some(rest) => format!("^{rest}"),
+            None => side.to_string(),
+        }
+    } else {
+        match side.strip_suffix('#') {
+            Some(rest) if !rest.ends_with('\\') => format!("{rest}$"),
+            _ => side.to_string(),
+        }
+    };
+
+    let open = match (is_before, negative) {
+        (true, false) => "(?<=",
+        (true, true) => "(?<!",
+        (false, false) => "(?=",
+        (false, true) => "(?!",
+    };
+
+    format!("{open}{})", unescape_slash(&side))
+}
+
+/// Desugar optional classes, and check repetitions of classes
+///
+/// - `<X?>` matches class `X` optionally, like `(?:<X>)?`. A class name cannot contain `?`, so this was not valid before
+/// - `<X>{2}`, `<X>{2,}` and `<X>{1,3}` repeat class `X`. Any other braces after a class are an error, rather than literal text
+///
+/// Classes are wrapped in a group when substituted, so repetitions apply to the whole class, even with multigraph phonemes
+///
+/// `pattern` argument must not contain spaces
+pub fn desugar_repetition(pattern: &str, line: usize) -> Result<String, Error> {
+    // Braces directly after class name
+    for captures in
+        regex!(r"(?<!\(\?)(?<!\(\?P)(?<!\\k)<([^<>]*)>(\{[^}]*\}?)").captures_iter(pattern)
+    {
+        let Ok(captures) = captures else {
+            continue;
+        };
+        let (Some(name), Some(repetition)) = (captures.get(1), captures.get(2)) else {
+            continue;
+        };
+        let repetition = repetition.as_str();
+
+        // Optional class cannot also be repeated
+        if name.as_str().ends_with('?') || !is_valid_repetition(repetition) {
+            return Err(Error::InvalidRepetition {
+                repetition: repetition.to_string(),
+                line,
+            });
+        }
+    }
+
+    // Optional class
+    Ok(regex!(r"(?<!\(\?)(?<!\(\?P)(?<!\\k)<([^<>?]+)\?>")
+        .replace_all(pattern, "(?:<$1>)?")
+        .to_string())
+}
+
+/// Get names of classes which are optional or repeated in pattern, with `?`, `*`, `+`, or braces
+///
+/// Includes classes alone in a group which is optional or repeated
+///
+/// `pattern` argument must be desugared with `desugar_repetition`
+pub fn quantified_classes(pattern: &str) -> Vec<String> {
+    regex!(r"(?<!\(\?)(?<!\(\?P)(?<!\\k)<([^<>]+)>[?*+{]|(?<!\\)\((?:\?:)?<([^<>]+)>\)[?*+{]")
+        .captures_iter(pattern)
+        .filter_map(|captures| {
+            let captures = captures.ok()?;
+            let name = captures.get(1).or_else(|| captures.get(2))?;
+            Some(name.as_str().to_string())
+        })
+        .collect()
+}
+
+/// Returns `true` if repetition is `{m}`, `{m,}` or `{m,n}`, where `m` is not greater than `n`
+fn is_valid_repetition(repetition: &str) -> bool {
+    let Some(inner) = repetition
+        .strip_prefix('{')
+        .and_then(|inner| inner.strip_suffix('}'))
+    else {
+        return false;
+    };
+
+    let (min, max) = match inner.split_once(',') {
+        Some((min, max)) => (min, Some(max)),
+        None => (inner, None),
+    };
+
+    let Ok(min) = min.parse::<usize>() else {
+        return false;
+    };
+
+    match max {
+        None | Some("") => true,
+        Some(max) => max.parse::<usize>().is_ok_and(|max| min <= max),
+    }
+}
+
+/// Split inline reason from rule, after the first `@` which is not escaped
+///
+/// Escaped `\@` is replaced with a literal `@` in the pattern
+///
+/// Eg. `! <N> / _k @ No nasal before 'k'`
+pub fn split_reason(rule: &str) -> (String, Option<&str>) {
+    let mut pattern = String::new();
+    let mut chars = rule.char_indices();
+
+    while let Some((index, ch)) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some((_, '@')) => pattern.push('@'),
+                Some((_, next)) => {
+                    pattern.push(ch);
+                    pattern.push(next);
+                }
+                None => pattern.push(ch),
+            },
+
+            // Start of reason
+            '@' => return (pattern, Some(rule[index + 1..].trim())),
+
+            _ => pattern.push(ch),
+        }
+    }
+
+    (pattern, None)
+}
+
+/// Split pattern at every instance of separator, which is not escaped, and not inside brackets or a class name
+fn split_top_level(pattern: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+
+    // Depth of round and square brackets
+    let mut depth = 0;
+    let mut in_class_name = false;
+    let mut chars = pattern.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            // Escaped character - Keep escape for next step
+            '\\' => {
+                push_last(&mut parts, ch);
+                if let Some(next) = chars.next() {
+                    push_last(&mut parts, next);
+                }
+                continue;
+            }
+
+            '(' | '[' if !in_class_name => depth += 1,
+            ')' | ']' if !in_class_name && depth > 0 => depth -= 1,
+
+            '<' | '⟨' if depth == 0 => in_class_name = true,
+            '>' | '⟩' if in_class_name => in_class_name = false,
+
+            // Separator
+            _ if ch == separator && depth == 0 && !in_class_name => {
+                parts.push(String::new());
+                continue;
+            }
+
+            _ => (),
+        }
+
+        push_last(&mut parts, ch);
+    }
+
+    parts
+}
+
+/// Push character to last string in list
+fn push_last(parts: &mut [String], ch: char) {
+    if let Some(last) = parts.last_mut() {
+        last.push(ch);
+    }
+}
+
+/// Replace escaped slashes (`\/`) with literal slashes
+fn unescape_slash(s: &str) -> String {
+    let mut output = String::new();
+    let mut chars = s.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            output.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('/') => output.push('/'),
+            Some(next) => {
+                output.push(ch);
+                output.push(next);
+            }
+            None => output.push(ch),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn desugar_repetition_works() {
+        assert_eq!(desugar_repetition("<V>{1,3}", 0).unwrap(), "<V>{1,3}");
+        assert_eq!(
+            desugar_repetition("<V>{2}<C>{1,}", 0).unwrap(),
+            "<V>{2}<C>{1,}"
+        );
+        assert_eq!(desugar_repetition("a{,3}", 0).unwrap(), "a{,3}");
+
+        assert_eq!(desugar_repetition("^<C?><V>$", 0).unwrap(), "^(?:<C>)?<V>$");
+        assert_eq!(
+            desugar_repetition("<C?><N?>", 0).unwrap(),
+            "(?:<C>)?(?:<N>)?"
+        );
+        // Groups are unchanged, so back-references still work
+        assert_eq!(desugar_repetition(r"(<C>)\1", 0).unwrap(), r"(<C>)\1");
+        assert_eq!(desugar_repetition("(<C>)", 0).unwrap(), "(<C>)");
+        assert_eq!(desugar_repetition("(?<x>a)", 0).unwrap(), "(?<x>a)");
+        assert_eq!(desugar_repetition("(?<!a)b", 0).unwrap(), "(?<!a)b");
+
+        for pattern in ["<V>{,3}", "<V>{3,1}", "<V>{a}", "<V>{}", "<V>{1", "<V?>{2}"] {
+            assert!(
+                matches!(
+                    desugar_repetition(pattern, 0),
+                    Err(Error::InvalidRepetition { .. })
+                ),
+                "{pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn quantified_classes_works() {
+        assert_eq!(
+            quantified_classes("(?:<C>)?<V>{1,3}<N>+(<L>)*<S>"),
+            ["C", "V", "N", "L"]
+        );
+        assert!(quantified_classes("(<C><V>)+(?<x>a)?").is_empty());
+    }
+
+    #[test]
+    fn desugar_environment_works() {
+        assert_eq!(desugar_environment("abc", 0).unwrap(), "abc");
+        assert_eq!(desugar_environment(r"a\/b", 0).unwrap(), "a/b");
+
+        assert_eq!(desugar_environment("n/_k", 0).unwrap(), "(?:n)(?=k)");
+        assert_eq!(desugar_environment("n/k_", 0).unwrap(), "(?<=k)(?:n)");
+        assert_eq!(desugar_environment("n/a_b", 0).unwrap(), "(?<=a)(?:n)(?=b)");
+        assert_eq!(desugar_environment("n/_!k", 0).unwrap(), "(?:n)(?!k)");
+        assert_eq!(desugar_environment("n/!k_", 0).unwrap(), "(?<!k)(?:n)");
+        assert_eq!(desugar_environment("a|b/_k", 0).unwrap(), "(?:a|b)(?=k)");
+
+        assert_eq!(
+            desugar_environment("<N>/_<Stop>", 0).unwrap(),
+            "(?:<N>)(?=<Stop>)"
+        );
+        assert_eq!(
+            desugar_environment("<_>/_<_>", 0).unwrap(),
+            "(?:<_>)(?=<_>)"
+        );
+        assert_eq!(
+            desugar_environment("[/]/_(a/b)", 0).unwrap(),
+            "(?:[/])(?=(a/b))"
+        );
+
+        assert_eq!(desugar_environment("s/#_", 0).unwrap(), "(?<=^)(?:s)");
+        assert_eq!(desugar_environment("s/_#", 0).unwrap(), "(?:s)(?=$)");
+        assert_eq!(desugar_environment("s/!#_", 0).unwrap(), "(?<!^)(?:s)");
+        assert_eq!(
+            desugar_environment("s/#a_t#", 0).unwrap(),
+            "(?<=^a)(?:s)(?=t$)"
+        );
+        assert_eq!(desugar_environment(r"s/_\#", 0).unwrap(), r"(?:s)(?=\#)");
+
+        assert!(matches!(
+            desugar_environment("a/_b/_c", 0),
+            Err(Error::EnvironmentMultipleSlash { .. })
+        ));
+        assert!(matches!(
+            desugar_environment("a/b", 0),
+            Err(Error::EnvironmentFocus { .. })
+        ));
+        assert!(matches!(
+            desugar_environment("a/_b_", 0),
+            Err(Error::EnvironmentFocus { .. })
+        ));
+    }
+
+    #[test]
+    fn split_reason_works() {
+        assert_eq!(split_reason("abc"), ("abc".to_string(), None));
+        assert_eq!(
+            split_reason("<N> / _k @ No nasal before 'k'"),
+            ("<N> / _k ".to_string(), Some("No nasal before 'k'"))
+        );
+        assert_eq!(split_reason(r"a\@b@c"), ("a@b".to_string(), Some("c")));
+        assert_eq!(split_reason(r"\d@"), (r"\d".to_string(), Some("")));
+    }
+
+    #[test]
+    fn desugar_position_works() {
+        assert_eq!(desugar_position("st", 0).unwrap(), "st");
+        assert_eq!(desugar_position("(?:a)", 0).unwrap(), "(?:a)");
+        assert_eq!(desugar_position("other:a", 0).unwrap(), "other:a");
+
+        assert_eq!(desugar_position("initial:st", 0).unwrap(), "^(?:st)");
+        assert_eq!(desugar_position("medial:h", 0).unwrap(), "(?<=.)(?:h)(?=.)");
+        assert_eq!(desugar_position("final:a|e", 0).unwrap(), "(?:a|e)$");
+        assert_eq!(desugar_position("onset:st", 0).unwrap(), "(?:st)(?=⟨V⟩)");
+        assert_eq!(
+            desugar_position("coda:rt", 0).unwrap(),
+            "(?<=⟨V⟩)(?:rt)(?!⟨V⟩)"
+        );
+
+        assert_eq!(
+            desugar_position("initial:n/_k", 0).unwrap(),
+            "^(?:(?:n)(?=k))"
+        );
+    }
+
+    #[test]
+    fn vowel_qualifier_works() {
+        assert_eq!(vowel_qualifier("onset:st"), Some("onset"));
+        assert_eq!(vowel_qualifier("coda:rt"), Some("coda"));
+        assert_eq!(vowel_qualifier("initial:ng"), None);
+        assert_eq!(vowel_qualifier("st"), None);
+    }
+}
@@ -0,0 +1,568 @@
+use std::{cmp::Reverse, time::Duration};
+
+use clap::{builder::PossibleValue, ValueEnum};
+use serde_json::json;
+
+use crate::{
+    types::{Error, FailReason, NoteLevel, TestResult, Warning},
+    Results,
+};
+
+/// Name of scheme file in reports, if not given
+const DEFAULT_FILENAME: &str = "phonet";
+
+/// Machine-readable format of test results
+#[derive(Clone, Copy, Debug)]
+pub enum ReportFormat {
+    /// JSON event stream, compatible with `cargo test -- --format json`
+    Json,
+    /// Line of `file:line:col: message` for each failure, for editor quickfix lists
+    Quickfix,
+    /// Plain text with a line for each test and note, without colors or times, to commit and diff
+    Canonical,
+    /// JUnit XML, with a test suite for each section, for CI dashboards
+    Junit,
+    /// CSV table with a row for each section and each test, for spreadsheets
+    Csv,
+}
+
+// Custom implementation, for argument aliases
+impl ValueEnum for ReportFormat {
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        // `help` values must mirror comments
+        Some(match self {
+            Self::Json => PossibleValue::new("json")
+                .aliases(["j", "libtest"])
+                .help("JSON event stream, compatible with `cargo test -- --format json`"),
+
+            Self::Quickfix => PossibleValue::new("quickfix")
+                .aliases(["q", "qf", "vim"])
+                .help(
+                    "Line of `file:line:col: message` for each failure, for editor quickfix lists",
+                ),
+
+            Self::Canonical => PossibleValue::new("canonical")
+                .aliases(["c", "golden", "plain"])
+                .help("Plain text with a line for each test and note, without colors or times, to commit and diff"),
+
+            Self::Junit => PossibleValue::new("junit")
+                .aliases(["x", "xml"])
+                .help("JUnit XML, with a test suite for each section, for CI dashboards"),
+
+            Self::Csv => PossibleValue::new("csv")
+                .aliases(["s", "spreadsheet"])
+                .help("CSV table with a row for each section and each test, for spreadsheets"),
+        })
+    }
+
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Json,
+            Self::Quickfix,
+            Self::Canonical,
+            Self::Junit,
+            Self::Csv,
+        ]
+    }
+}
+
+/// Tallies of failed tests, by reason and by rule
+///
+/// Create with `Results::stats()`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Text of reason, and count of failed tests with reason
+    ///
+    /// Sorted by count, most first
+    pub reasons: Vec<(String, usize)>,
+    /// Index of rule, and count of failed tests which were made invalid by rule
+    ///
+    /// Sorted by count, most first
+    pub rules: Vec<(usize, usize)>,
+}
+
+/// Aggregate results of tests under a note
+///
+/// Create with `Results::sections()`
+#[derive(Debug, PartialEq, Eq)]
+pub struct Section {
+    /// Text of note which starts section, or `None` for tests before the first note
+    pub name: Option<String>,
+    /// Amount of passed tests
+    pub passed: usize,
+    /// Amount of failed tests
+    pub failed: usize,
+    /// Total time taken to run tests
+    pub duration: Duration,
+}
+
+impl Results {
+    /// Group tests into sections, which each start at a note
+    ///
+    /// Sections without any tests are not included
+    pub fn sections(&self) -> Vec<Section> {
+        self.section_tests()
+            .into_iter()
+            .map(|(section, _)| section)
+            .collect()
+    }
+
+    /// Group tests into sections, with the tests of each section
+    fn section_tests(&self) -> Vec<(Section, Vec<&TestResult>)> {
+        let mut sections = vec![(
+            Section {
+                name: None,
+                passed: 0,
+                failed: 0,
+                duration: Duration::ZERO,
+            },
+            Vec::new(),
+        )];
+
+        for item in &self.list {
+            match item {
+                TestResult::Note(note) => sections.push((
+                    Section {
+                        name: Some(note.text.to_string()),
+                        passed: 0,
+                        failed: 0,
+                        duration: Duration::ZERO,
+                    },
+                    Vec::new(),
+                )),
+
+                TestResult::Test { pass, duration, .. } => {
+                    let Some((section, tests)) = sections.last_mut() else {
+                        continue;
+                    };
+                    if *pass {
+                        section.passed += 1;
+                    } else {
+                        section.failed += 1;
+                    }
+                    section.duration += *duration;
+                    tests.push(item);
+                }
+            }
+        }
+
+        sections.retain(|(_, tests)| !tests.is_empty());
+        sections
+    }
+
+    /// Count failed tests, by reason and by rule
+    ///
+    /// Tests which are valid, but should be invalid, are not made invalid by any rule
+    pub fn stats(&self) -> Stats {
+        let mut reasons: Vec<(String, usize)> = Vec::new();
+        let mut rules: Vec<(usize, usize)> = Vec::new();
+
+        for item in &self.list {
+            let TestResult::Test {
+                pass: false,
+                reason,
+                rule,
+                ..
+            } = item
+            else {
+                continue;
+            };
+
+            // Reason
+            if !matches!(reason, FailReason::Passed) {
+                match reasons.iter_mut().find(|(text, _)| text == reason.text()) {
+                    Some((_, count)) => *count += 1,
+                    None => reasons.push((reason.text().to_string(), 1)),
+                }
+            }
+
+            // Rule
+            if let Some(rule) = rule {
+                match rules.iter_mut().find(|(index, _)| index == rule) {
+                    Some((_, count)) => *count += 1,
+                    None => rules.push((*rule, 1)),
+                }
+            }
+        }
+
+        // Most first, keeping order of first failure for equal counts
+        reasons.sort_by_key(|(_, count)| Reverse(*count));
+        rules.sort_by_key(|(_, count)| Reverse(*count));
+
+        Stats { reasons, rules }
+    }
+
+    /// Format results as a machine-readable report
+    pub fn report(&self, format: ReportFormat) -> String {
+        self.report_with(format, None)
+    }
+
+    /// Format results as a machine-readable report, with name of scheme file
+    ///
+    /// Name of file is only used for `Quickfix` and `Junit` formats, and defaults to `phonet`
+    pub fn report_with(&self, format: ReportFormat, filename: Option<&str>) -> String {
+        match format {
+            ReportFormat::Json => self.json_report(),
+            ReportFormat::Quickfix => self.quickfix_report(filename.unwrap_or(DEFAULT_FILENAME)),
+            ReportFormat::Canonical => self.canonical_report(),
+            ReportFormat::Junit => self.junit_report(filename.unwrap_or(DEFAULT_FILENAME)),
+            ReportFormat::Csv => self.csv_report(),
+        }
+    }
+
+    /// Format results as JUnit XML, with a test suite for each section
+    ///
+    /// Tests before the first note are in a test suite with the name of the file
+    fn junit_report(&self, filename: &str) -> String {
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+        output.push_str(&format!(
+            "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{}\">\n",
+            escape_xml(filename),
+            self.test_count(),
+            self.fail_count,
+            self.duration.as_secs_f64()
+        ));
+
+        for (section, tests) in self.section_tests() {
+            output.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{}\">\n",
+                escape_xml(section.name.as_deref().unwrap_or(filename)),
+                section.passed + section.failed,
+                section.failed,
+                section.duration.as_secs_f64()
+            ));
+
+            for test in tests {
+                let TestResult::Test {
+                    intent,
+                    word,
+                    label,
+                    pass,
+                    reason,
+                    target,
+                    duration,
+                    ..
+                } = test
+                else {
+                    continue;
+                };
+
+                let testcase = format!(
+                    "<testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+                    escape_xml(&test_name(
+                        *intent,
+                        word,
+                        label.as_deref(),
+                        target.is_some()
+                    )),
+                    escape_xml(filename),
+                    duration.as_secs_f64()
+                );
+
+                if *pass {
+                    output.push_str(&format!("    {testcase} />\n"));
+                } else {
+                    output.push_str(&format!(
+                        "    {testcase}>\n      <failure message=\"{}\" />\n    </testcase>\n",
+                        escape_xml(reason.text())
+                    ));
+                }
+            }
+
+            output.push_str("  </testsuite>\n");
+        }
+
+        output.push_str("</testsuites>\n");
+        output
+    }
+
+    /// Format results as CSV, with a row for each section, followed by a row for each test in it
+    ///
+    /// Columns: `type` (`section` or `test`), `section`, `name`, `passed`, `failed`, `reason`, `exec_time`
+    fn csv_report(&self) -> String {
+        let mut output = String::from("type,section,name,passed,failed,reason,exec_time\n");
+
+        for (section, tests) in self.section_tests() {
+            let section_name = section.name.as_deref().unwrap_or("");
+
+            output.push_str(&csv_row(&[
+                "section",
+                section_name,
+                "",
+                &section.passed.to_string(),
+                &section.failed.to_string(),
+                "",
+                &section.duration.as_secs_f64().to_string(),
+            ]));
+
+            for test in tests {
+                let TestResult::Test {
+                    intent,
+                    word,
+                    label,
+                    pass,
+                    reason,
+                    target,
+                    duration,
+                    ..
+                } = test
+                else {
+                    continue;
+                };
+
+                output.push_str(&csv_row(&[
+                    "test",
+                    section_name,
+                    &test_name(*intent, word, label.as_deref(), target.is_some()),
+                    if *pass { "1" } else { "0" },
+                    if *pass { "0" } else { "1" },
+                    if *pass { "" } else { reason.text() },
+                    &duration.as_secs_f64().to_string(),
+                ]));
+            }
+        }
+
+        output
+    }
+
+    /// Format each test and note as a line of plain text, in order of definition, with counts at the end
+    ///
+    /// Output only changes when results change: Times, lines, and rule indexes are not included, and lines end with LF
+    ///
+    /// Eg. `FAIL ?! tatso  @ No 'ts'`
+    fn canonical_report(&self) -> String {
+        let mut output = String::new();
+
+        for item in &self.list {
+            let line = match item {
+                TestResult::Note(note) => match note.level {
+                    NoteLevel::Warning => format!("*! {}", note.text),
+                    NoteLevel::Info | NoteLevel::Heading => format!("* {}", note.text),
+                },
+
+                TestResult::Test {
+                    intent,
+                    word,
+                    label,
+                    pass,
+                    reason,
+                    target,
+                    ..
+                } => {
+                    let name = test_name(*intent, word, label.as_deref(), target.is_some());
+                    if *pass {
+                        format!("pass {name}")
+                    } else {
+                        format!("FAIL {name}  @ {}", reason.text())
+                    }
+                }
+            };
+
+            output.push_str(&line);
+            output.push('\n');
+        }
+
+        output.push_str(&format!(
+            "{} passed, {} failed\n",
+            self.pass_count(),
+            self.fail_count
+        ));
+        output
+    }
+
+    /// Format each failed test as `file:line:col: error: message`
+    ///
+    /// Tests without a line (Eg. from command line) use the first line
+    fn quickfix_report(&self, filename: &str) -> String {
+        self.list
+            .iter()
+            .filter_map(|item| match item {
+                TestResult::Test {
+                    intent,
+                    word,
+                    label,
+                    pass: false,
+                    reason,
+                    target,
+                    line,
+                    ..
+                } => Some(quickfix_line(
+                    filename,
+                    *line,
+                    "error",
+                    &format!(
+                        "{}: {}",
+                        test_name(*intent, word, label.as_deref(), target.is_some()),
+                        reason.text()
+                    ),
+                )),
+                _ => None,
+            })
+            .chain(
+                self.warnings
+                    .iter()
+                    .map(|warning| warning.quickfix(filename)),
+            )
+            .collect()
+    }
+
+    /// Format results as a JSON event stream, one event per line
+    ///
+    /// Mirrors the output of `cargo test -- --format json`, so existing tools can read it
+    fn json_report(&self) -> String {
+        let mut events = vec![json!({
+            "type": "suite",
+            "event": "started",
+            "test_count": self.test_count(),
+        })];
+
+        for item in &self.list {
+            // Notes are not tests
+            let TestResult::Test {
+                intent,
+                word,
+                label,
+                pass,
+                reason,
+                target,
+                duration,
+                ..
+            } = item
+            else {
+                continue;
+            };
+
+            let name = test_name(*intent, word, label.as_deref(), target.is_some());
+
+            events.push(json!({
+                "type": "test",
+                "event": "started",
+                "name": name,
+            }));
+
+            events.push(if *pass {
+                json!({
+                    "type": "test",
+                    "event": "ok",
+                    "name": name,
+                    "exec_time": duration.as_secs_f64(),
+                })
+            } else {
+                json!({
+                    "type": "test",
+                    "event": "failed",
+                    "name": name,
+                    "exec_time": duration.as_secs_f64(),
+                    "stdout": format!("{}\n", reason.text()),
+                })
+            });
+        }
+
+        let stats = self.stats();
+
+        events.push(json!({
+            "type": "suite",
+            "event": if self.fail_count == 0 { "ok" } else { "failed" },
+            "passed": self.pass_count(),
+            "failed": self.fail_count,
+            "ignored": 0,
+            "measured": 0,
+            "filtered_out": 0,
+            "exec_time": self.duration.as_secs_f64(),
+            "stats": {
+                "reasons": stats
+                    .reasons
+                    .iter()
+                    .map(|(reason, failures)| json!({ "reason": reason, "failures": failures }))
+                    .collect::<Vec<_>>(),
+                "rules": stats
+                    .rules
+                    .iter()
+                    .map(|(rule, failures)| json!({ "rule": rule, "failures": failures }))
+                    .collect::<Vec<_>>(),
+            },
+            "warnings": self
+                .warnings
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect::<Vec<_>>(),
+            "sections": self
+                .sections()
+                .iter()
+                .map(|section| {
+                    json!({
+                        "name": section.name,
+                        "passed": section.passed,
+                        "failed": section.failed,
+                        "exec_time": section.duration.as_secs_f64(),
+                    })
+                })
+                .collect::<Vec<_>>(),
+        }));
+
+        events
+            .iter()
+            .map(|event| event.to_string() + "\n")
+            .collect()
+    }
+}
+
+/// Name of test in reports, using scheme syntax
+///
+/// Eg. `?+ taso`, `?+ [gen-sg form] tanem` with label, or `?^+ taso` with target rule
+fn test_name(intent: bool, word: &str, label: Option<&str>, target: bool) -> String {
+    let intent = if intent { '+' } else { '!' };
+    let target = if target { "^" } else { "" };
+
+    match label {
+        Some(label) => format!("?{target}{intent} [{label}] {word}"),
+        None => format!("?{target}{intent} {word}"),
+    }
+}
+
+impl Error {
+    /// Format error as `file:line:col: error: message`, for editor quickfix lists
+    pub fn quickfix(&self, filename: &str) -> String {
+        quickfix_line(filename, self.line(), "error", &self.to_string())
+    }
+}
+
+impl Warning {
+    /// Format warning as `file:line:col: warning: message`, for editor quickfix lists
+    pub fn quickfix(&self, filename: &str) -> String {
+        quickfix_line(filename, Some(self.line()), "warning", &self.to_string())
+    }
+}
+
+/// Escape text for an XML attribute
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Format single row of CSV report, quoting fields which contain commas, quotes, or line breaks
+fn csv_row(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+
+    fields.join(",") + "\n"
+}
+
+/// Format single line of quickfix report
+///
+/// Column is always 1, as statements are not tracked by column
+fn quickfix_line(filename: &str, line: Option<usize>, kind: &str, message: &str) -> String {
+    format!("{filename}:{}:1: {kind}: {message}\n", line.unwrap_or(1))
+}
@@ -1,5 +1,17 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    thread,
+    time::{Duration, Instant},
+};
+
+use fancy_regex::RuntimeError;
+use fancy_regex_macro::regex;
+
 use crate::{
-    types::{Rule, TestDefinition, TestResult},
+    types::{
+        DisplayOptions, Note, NoteLevel, Rule, RunOptions, TestDefinition, TestResult, Warning,
+    },
     DisplayLevel::{self, *},
     Phonet,
 };
@@ -14,86 +26,156 @@ pub struct Results {
     pub list: Vec<TestResult>,
     /// Amount of failed tests
     pub fail_count: u32,
+    /// Time taken to run all tests
+    pub duration: Duration,
+    /// Rules which tests were run with
+    pub rules: Vec<Rule>,
+    /// Counts of positive (`?+`) tests
+    pub positive: IntentCounts,
+    /// Counts of negative (`?!`) tests
+    pub negative: IntentCounts,
+    /// Problems found while running, which did not stop tests
+    pub warnings: Vec<Warning>,
+}
+
+/// Counts of passed and failed tests, of a single intent
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IntentCounts {
+    /// Amount of passed tests
+    pub passed: usize,
+    /// Amount of failed tests
+    pub failed: usize,
+}
+
+impl IntentCounts {
+    /// Get total amount of tests
+    pub fn total(&self) -> usize {
+        self.passed + self.failed
+    }
 }
 
 impl Results {
     /// Run tests, return results
     pub fn run(scheme: &Phonet) -> Results {
+        Self::run_jobs(scheme, 1)
+    }
+
+    /// Run tests on multiple threads, return results
+    ///
+    /// Results are always in order of definition, regardless of amount of jobs
+    pub fn run_jobs(scheme: &Phonet, jobs: usize) -> Results {
+        Self::run_with(
+            scheme,
+            &RunOptions {
+                jobs,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Run tests with options, return results
+    pub fn run_with(scheme: &Phonet, options: &RunOptions) -> Results {
         // No tests
         if scheme.tests.is_empty() {
             return Results {
                 list: Vec::new(),
                 fail_count: 0,
+                duration: Duration::ZERO,
+                rules: scheme.rules.clone(),
+                positive: IntentCounts::default(),
+                negative: IntentCounts::default(),
+                warnings: find_run_warnings(scheme),
             };
         }
 
-        // Builders
-        let mut list = vec![];
+        // Start timer
+        let start = Instant::now();
+
+        let jobs = options.jobs.clamp(1, scheme.tests.len());
+        let use_cache = !options.no_cache;
+
+        let list: Vec<TestResult> = if jobs == 1 {
+            // Single thread
+            let mut cache = Cache::new();
+            scheme
+                .tests
+                .iter()
+                .map(|test| run_test(test, scheme, use_cache.then_some(&mut cache)))
+                .collect()
+        } else {
+            // Split tests into one continuous chunk for each thread
+            // Chunks are joined in order, so order does not depend on which thread finishes first
+            let chunk_size = scheme.tests.len().div_ceil(jobs);
+
+            thread::scope(|scope| {
+                let handles: Vec<_> = scheme
+                    .tests
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        scope.spawn(move || {
+                            // Each thread has its own cache
+                            let mut cache = Cache::new();
+                            chunk
+                                .iter()
+                                .map(|test| run_test(test, scheme, use_cache.then_some(&mut cache)))
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().expect("Failed to run tests on thread"))
+                    .collect()
+            })
+        };
+
+        // Count passes and fails
         let mut fail_count = 0;
-        let mut max_word_len = 0;
-
-        // Loop tests
-        for test in &scheme.tests {
-            match test {
-                // Note - simply add to list
-                TestDefinition::Note(note) => list.push(TestResult::Note(note.to_string())),
-
-                // Test - Validate test, check validity with intent, create reason for failure
-                TestDefinition::Test { intent, word } => {
-                    // Validate test
-                    let validity = validate_test(word, &scheme.rules);
-
-                    // Check if validity status with test intent
-                    let pass = !(validity.is_valid() ^ intent);
-
-                    // Create reason
-                    let reason = if !pass {
-                        // Test failed - Some reason
-                        FailReason::from(validity, &scheme.reasons)
-                    } else {
-                        // Test passed - No reason for failure needed
-                        Passed
-                    };
+        let mut positive = IntentCounts::default();
+        let mut negative = IntentCounts::default();
 
-                    // Increase fail count if failed
-                    if !pass {
-                        fail_count += 1;
-                    }
+        for item in &list {
+            let TestResult::Test { intent, pass, .. } = item else {
+                continue;
+            };
 
-                    // Increase max length if word is longer than current max
-                    if word.len() > max_word_len {
-                        max_word_len = word.len();
-                    }
+            // Increase fail count if failed
+            if !pass {
+                fail_count += 1;
+            }
 
-                    // Add test result to list
-                    list.push(TestResult::Test {
-                        intent: *intent,
-                        word: word.to_string(),
-                        pass,
-                        reason,
-                    });
-                }
+            // Increase counts of test intent
+            let counts = if *intent {
+                &mut positive
+            } else {
+                &mut negative
+            };
+            if *pass {
+                counts.passed += 1;
+            } else {
+                counts.failed += 1;
             }
         }
 
-        Results { list, fail_count }
+        Results {
+            list,
+            fail_count,
+            duration: start.elapsed(),
+            rules: scheme.rules.clone(),
+            positive,
+            negative,
+            warnings: find_run_warnings(scheme),
+        }
     }
 
     /// Get maximum length of all test words
     fn max_word_len(&self, display_level: DisplayLevel) -> usize {
         self.list
             .iter()
+            .filter(|x| display_level.includes(x))
             .map(|x| match x {
-                // Test - Check display level
-                TestResult::Test { word, pass, .. } => match display_level {
-                    // Always include
-                    ShowAll => word.len(),
-                    // Only include if failed
-                    NotesAndFails | JustFails if !pass => word.len(),
-                    // Don't include
-                    _ => 0,
-                },
-
+                TestResult::Test { word, label, .. } => display_name(word, label).chars().count(),
                 TestResult::Note(_) => 0,
             })
             .max()
@@ -109,90 +191,217 @@ impl Results {
             .count()
     }
 
+    /// Get count of notes in list
+    pub fn note_count(&self) -> usize {
+        self.list
+            .iter()
+            .filter(|item| matches!(item, TestResult::Note(_)))
+            .count()
+    }
+
+    /// Get count of passed tests
+    pub fn pass_count(&self) -> usize {
+        self.test_count() - self.fail_count as usize
+    }
+
+    /// Get percentage of tests which passed, from 0 to 100
+    ///
+    /// Returns `None` if no tests ran
+    pub fn pass_rate(&self) -> Option<f64> {
+        let total = self.test_count();
+        if total == 0 {
+            return None;
+        }
+        Some(self.pass_count() as f64 / total as f64 * 100.0)
+    }
+
+    /// Get tests which took longest to run, slowest first, with up to `count` tests
+    pub fn slowest(&self, count: usize) -> Vec<&TestResult> {
+        let mut tests: Vec<&TestResult> = self
+            .list
+            .iter()
+            .filter(|item| matches!(item, TestResult::Test { .. }))
+            .collect();
+
+        tests.sort_by_key(|item| match item {
+            TestResult::Test { duration, .. } => std::cmp::Reverse(*duration),
+            TestResult::Note(_) => std::cmp::Reverse(Duration::ZERO),
+        });
+        tests.truncate(count);
+        tests
+    }
+
+    /// Get summary of results as a single machine-readable line
+    ///
+    /// Eg. `phonet: 412 passed, 3 failed, 2 notes, 31ms`
+    pub fn summary_line(&self) -> String {
+        format!(
+            "phonet: {passes} passed, {fails} failed, {notes} notes, {ms}ms",
+            passes = self.pass_count(),
+            fails = self.fail_count,
+            notes = self.note_count(),
+            ms = self.duration.as_millis(),
+        )
+    }
+
     /// Display results to standard output
     ///
     /// This can be implemented manually
     pub fn display(&self, display_level: DisplayLevel, no_color: bool) {
+        self.display_with(&DisplayOptions {
+            display_level,
+            no_color,
+            ..Default::default()
+        });
+    }
+
+    /// Display results to standard output, with more options
+    ///
+    /// This can be implemented manually
+    pub fn display_with(&self, options: &DisplayOptions) {
+        let display_level = options.display_level;
+        let no_color = options.no_color;
+
+        // Problems found while running
+        for warning in &self.warnings {
+            if no_color {
+                println!("Warning: {warning}");
+            } else {
+                println!("\x1b[33;1mWarning:\x1b[0;33m {warning}\x1b[0m");
+            }
+        }
+
+        // No rules - Every word is valid
+        // Not shown with summary only, like tests
+        if self.rules.is_empty() && !matches!(display_level, JustSummary | HideAll) {
+            let message = if self.test_count() == 0 {
+                "No rules are defined, so every word is valid."
+            } else {
+                "No rules are defined, so every word is valid, and every test with `?!` fails. Add rules with `+` or `!`"
+            };
+
+            if no_color {
+                println!("{message}");
+            } else {
+                println!("\x1b[33m{message}\x1b[0m");
+            }
+        }
+
         // No tests
         if self.test_count() == 0 {
             if no_color {
-                println!("No tests ran.");
+                println!("No tests ran. Add tests with `?+` or `?!`");
             } else {
-                println!("\x1b[33mNo tests ran.\x1b[0m");
+                println!("\x1b[33mNo tests ran. Add tests with `?+` or `?!`\x1b[0m");
             }
             return;
         }
 
+        // Only show summary counts
+        if let JustSummary = display_level {
+            self.display_counts(no_color);
+            return;
+        }
+
         // Get maximum length of all test words
         let max_word_len = self.max_word_len(display_level);
 
         // Loop result list
         for item in &self.list {
+            // Skip if not required by display level
+            if !display_level.includes(item) {
+                continue;
+            }
+            if let TestResult::Note(note) = item {
+                if !options.notes.includes(note) {
+                    continue;
+                }
+            }
+
             match item {
                 // Display note
-                TestResult::Note(note) => match display_level {
-                    // Always show - Print note
-                    ShowAll | NotesAndFails => {
-                        if no_color {
-                            println!("{note}")
-                        } else {
-                            println!("\x1b[34m{note}\x1b[0m")
-                        }
-                    }
-
-                    // Else skip
-                    _ => (),
-                },
+                TestResult::Note(note) => println!("{}", format_note(note, no_color)),
 
                 // Display test
                 TestResult::Test {
                     intent,
                     word,
+                    label,
                     pass,
                     reason,
+                    rule,
+                    line,
+                    duration,
+                    ..
                 } => {
-                    // Skip if not required by display level
-                    if match display_level {
-                        // Always show
-                        ShowAll => false,
-                        // Only show if failed
-                        NotesAndFails | JustFails if !pass => false,
-                        // Else skip
-                        _ => true,
-                    } {
-                        continue;
-                    }
+                    // Include label with word
+                    let word = display_name(word, label);
 
-                    // Format reason
-                    let reason = match &reason {
-                        Passed => "",
-                        ShouldBeInvalid => {
+                    // Mark slow test
+                    let slow = match options.slow_threshold {
+                        Some(threshold) if *duration >= threshold => {
+                            let slow = format!("SLOW {}", format_duration(*duration));
                             if no_color {
-                                "Valid, but should be invalid"
+                                format!("  {slow}")
                             } else {
-                                "\x1b[33mValid, but should be invalid\x1b[0m"
+                                format!("  \x1b[33;1m{slow}\x1b[0m")
                             }
                         }
-                        NoReasonGiven => "No reason given",
-                        Custom(reason) => reason,
+                        _ => String::new(),
+                    };
+
+                    // Format reason
+                    let match_error;
+                    let reason = match &reason {
+                        ShouldBeInvalid if !no_color => {
+                            "\x1b[33mValid, but should be invalid\x1b[0m"
+                        }
+                        FailReason::Timeout { .. } if !no_color => {
+                            "\x1b[31mRule exceeded backtrack limit\x1b[0m"
+                        }
+                        FailReason::MatchError { err, .. } if !no_color => {
+                            match_error = format!("\x1b[31m{err}\x1b[0m");
+                            &match_error
+                        }
+                        _ => reason.text(),
                     };
 
                     // Display test status
-                    if no_color {
+                    if let (DimPasses, true, false) = (display_level, *pass, no_color) {
                         println!(
-                            " {intent} {word}{space}  {result} {reason}",
+                            "  \x1b[2m{intent} {word}{space}  pass\x1b[0m{slow}",
+                            intent = if *intent { "✔" } else { "✗" },
+                            space = " ".repeat(max_word_len - word.chars().count()),
+                        );
+                    } else if no_color {
+                        println!(
+                            " {intent} {word}{space}  {result} {reason}{slow}",
                             intent = if *intent { "✔" } else { "✗" },
                             space = " ".repeat(max_word_len - word.chars().count()),
                             result = if *pass { "pass" } else { "FAIL" },
                         );
                     } else {
                         println!(
-              "  \x1b[{intent}\x1b[0m {word}{space}  \x1b[1;{result} \x1b[0;3;1m{reason}\x1b[0m",
+              "  \x1b[{intent}\x1b[0m {word}{space}  \x1b[1;{result} \x1b[0;3;1m{reason}\x1b[0m{slow}",
               intent = if *intent { "36m✔" } else { "35m✗" },
               space = " ".repeat(max_word_len - word.chars().count()),
               result = if *pass { "32mpass" } else { "31mFAIL" },
             );
                     }
+
+                    // Display location of failed test
+                    if !pass {
+                        if let Some(line) = line {
+                            self.display_location(options.filename.as_deref(), *line, no_color);
+                        }
+                    }
+
+                    // Display pattern of rule which failed test
+                    if options.verbose_failures && !pass {
+                        if let Some(rule) = rule.and_then(|rule| self.rules.get(rule)) {
+                            self.display_rule(rule, no_color);
+                        }
+                    }
                 }
             }
         }
@@ -221,40 +430,412 @@ impl Results {
                 );
             }
         }
+
+        self.display_rate(no_color);
+        self.display_slowest(options.slowest, no_color);
     }
+
+    /// Display tests which took longest to run, with duration and line, to standard output
+    fn display_slowest(&self, count: usize, no_color: bool) {
+        let slowest = self.slowest(count);
+        if slowest.is_empty() {
+            return;
+        }
+
+        if no_color {
+            println!("Slowest tests:");
+        } else {
+            println!("\x1b[1mSlowest tests:\x1b[0m");
+        }
+
+        for item in slowest {
+            let TestResult::Test {
+                word,
+                label,
+                line,
+                duration,
+                ..
+            } = item
+            else {
+                continue;
+            };
+
+            let word = display_name(word, label);
+            let line = match line {
+                Some(line) => format!(" (line {line})"),
+                None => String::new(),
+            };
+            let duration = format_duration(*duration);
+
+            if no_color {
+                println!("  {duration:>7}  {word}{line}");
+            } else {
+                println!("  \x1b[33m{duration:>7}\x1b[0m  {word}\x1b[2m{line}\x1b[0m");
+            }
+        }
+    }
+
+    /// Display location of test in scheme file to standard output
+    ///
+    /// Eg. `lang.phonet:182`, or `line 182` without filename
+    fn display_location(&self, filename: Option<&str>, line: usize, no_color: bool) {
+        let location = match filename {
+            Some(filename) => format!("{filename}:{line}"),
+            None => format!("line {line}"),
+        };
+
+        if no_color {
+            println!("      at: {location}");
+        } else {
+            println!("      \x1b[2mat:\x1b[0m \x1b[4m{location}\x1b[0m");
+        }
+    }
+
+    /// Display rule as written, with line, and pattern of rule (with classes substituted) to standard output
+    fn display_rule(&self, rule: &Rule, no_color: bool) {
+        let intent = if rule.intent { '+' } else { '!' };
+
+        if no_color {
+            println!("      rule: {intent} {} (line {})", rule.source, rule.line);
+            println!("      pattern: {}", rule.pattern);
+        } else {
+            println!(
+                "      \x1b[2mrule:\x1b[0m \x1b[33m{intent} {}\x1b[0;2m (line {})\x1b[0m",
+                rule.source, rule.line
+            );
+            println!(
+                "      \x1b[2mpattern:\x1b[0m \x1b[33m{}\x1b[0m",
+                rule.pattern
+            );
+        }
+    }
+
+    /// Display count of passed and failed tests to standard output
+    fn display_counts(&self, no_color: bool) {
+        let passes = self.pass_count();
+        let fails = self.fail_count;
+
+        if no_color {
+            println!("{passes} passed, {fails} failed");
+        } else {
+            println!(
+                "\x1b[32;1m{passes} passed\x1b[0m, \x1b[{color}1m{fails} failed\x1b[0m",
+                color = if fails == 0 { "" } else { "31;" },
+            );
+        }
+
+        self.display_rate(no_color);
+    }
+
+    /// Display pass percentage, and counts of each intent, to standard output
+    ///
+    /// Eg. `98.1% passed (✔ 40/41, ✗ 12/12)`
+    fn display_rate(&self, no_color: bool) {
+        let Some(rate) = self.pass_rate() else {
+            return;
+        };
+
+        let positive = format!("✔ {}/{}", self.positive.passed, self.positive.total());
+        let negative = format!("✗ {}/{}", self.negative.passed, self.negative.total());
+
+        if no_color {
+            println!("{rate:.1}% passed ({positive}, {negative})");
+        } else {
+            println!(
+                "\x1b[2m{rate:.1}% passed (\x1b[0;36m{positive}\x1b[0;2m, \x1b[0;35m{negative}\x1b[0;2m)\x1b[0m"
+            );
+        }
+    }
+}
+
+/// Find references to reasons and rules which do not exist
+///
+/// These are otherwise ignored while running: Unknown reasons are not given, and unknown target rules allow every word
+fn find_run_warnings(scheme: &Phonet) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    for rule in &scheme.rules {
+        if let Some(reason) = rule.reason_ref {
+            if scheme.reasons.get(reason).is_none() {
+                warnings.push(Warning::UnknownReason {
+                    reason,
+                    line: rule.line,
+                });
+            }
+        }
+    }
+
+    for test in &scheme.tests {
+        if let TestDefinition::Test {
+            target: Some(target),
+            line,
+            ..
+        } = test
+        {
+            if scheme.rules.get(*target).is_none() {
+                warnings.push(Warning::UnknownTargetRule {
+                    target: *target,
+                    line: line.unwrap_or_default(),
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Validity of each word which was already validated, with target rule
+type Cache = HashMap<(String, Option<usize>), ValidStatus>;
+
+/// Run a single test
+///
+/// If `cache` is given, validity of repeated words is reused
+fn run_test(test: &TestDefinition, scheme: &Phonet, cache: Option<&mut Cache>) -> TestResult {
+    match test {
+        // Note - simply add to list
+        TestDefinition::Note(note) => TestResult::Note(note.clone()),
+
+        // Test - Validate test, check validity with intent, create reason for failure
+        TestDefinition::Test {
+            intent,
+            word,
+            label,
+            target,
+            line,
+        } => {
+            // Start timer
+            let start = Instant::now();
+
+            // Validate test without diacritics, with only target rule if given
+            let stripped = scheme.strip_diacritics(word);
+            let validate = |word: &str| match target {
+                Some(target) => validate_with_rule(word, &scheme.rules, *target),
+                None => validate_test(word, &scheme.rules),
+            };
+            let validity = match cache {
+                Some(cache) => cache
+                    .entry((stripped, *target))
+                    .or_insert_with_key(|(word, _)| validate(word))
+                    .clone(),
+                None => validate(&stripped),
+            };
+
+            // Check if validity status with test intent
+            // Test always fails if validity is not known
+            let pass = match validity {
+                ValidStatus::Timeout { .. } | ValidStatus::MatchError { .. } => false,
+                _ => !(validity.is_valid() ^ intent),
+            };
+
+            // Rule which made word invalid
+            let rule = validity.rule();
+
+            // Create reason
+            let reason = if !pass {
+                // Test failed - Some reason
+                FailReason::from(validity, &scheme.reasons)
+            } else {
+                // Test passed - No reason for failure needed
+                Passed
+            };
+
+            TestResult::Test {
+                intent: *intent,
+                word: word.to_string(),
+                label: label.clone(),
+                pass,
+                reason,
+                rule,
+                target: *target,
+                line: *line,
+                duration: start.elapsed(),
+            }
+        }
+    }
+}
+
+/// Get word of test for display, with label before it, if any
+///
+/// Eg. `[gen-sg form] tanem`
+fn display_name(word: &str, label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!("[{label}] {word}"),
+        None => word.to_string(),
+    }
+}
+
+/// Format duration of test for display, with a suitable unit
+///
+/// Eg. `1.2s`, `340ms`, or `85µs`
+fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() > 0 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else if duration.as_millis() > 0 {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{}µs", duration.as_micros())
+    }
+}
+
+/// Format note for display, using level and formatting markers
+///
+/// - `== Header ==`: Header
+/// - `---`: Horizontal rule (3 or more dashes only)
+/// - `*emphasis*` and `**strong**`: Inline emphasis
+///
+/// Warnings are displayed with `!` before them. Markers are kept as-is, if `no_color` is true
+fn format_note(note: &Note, no_color: bool) -> String {
+    let level = note.level;
+    let note = note.text.as_str();
+
+    // Header
+    if level == NoteLevel::Heading {
+        let header = note.trim_matches('=').trim();
+        return if no_color {
+            format!("\n{note}")
+        } else {
+            format!("\n\x1b[1;4;34m{header}\x1b[0m")
+        };
+    }
+
+    // Warning
+    if level == NoteLevel::Warning {
+        return if no_color {
+            format!("! {note}")
+        } else {
+            format!("\x1b[33;1m! \x1b[0;33m{note}\x1b[0m")
+        };
+    }
+
+    // Horizontal rule
+    if note.len() >= 3 && note.chars().all(|ch| ch == '-') {
+        return if no_color {
+            "-".repeat(40)
+        } else {
+            format!("\x1b[2;34m{}\x1b[0m", "─".repeat(40))
+        };
+    }
+
+    if no_color {
+        return note.to_string();
+    }
+
+    // Inline emphasis
+    let note = regex!(r"\*\*(\S(?:.*?\S)?)\*\*").replace_all(note, "\x1b[1m$1\x1b[22m");
+    let note = regex!(r"\*(\S(?:.*?\S)?)\*").replace_all(&note, "\x1b[3m$1\x1b[23m");
+
+    format!("\x1b[34m{note}\x1b[0m")
 }
 
 /// Reason for failure variants
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum FailReason {
     /// Test passed, do not display reason
     Passed,
     /// No reason was given for rule for test failing
-    NoReasonGiven,
+    NoReasonGiven {
+        /// Index of rule which made word invalid
+        rule: usize,
+        /// Byte span of match of negative rule in word
+        ///
+        /// `None` for positive rule, which did not match
+        span: Option<Range<usize>>,
+    },
     /// Test was valid, but should have been invalid
     ShouldBeInvalid,
+    /// Rule exceeded backtrack limit while matching word, so validity is not known
+    Timeout {
+        /// Index of rule which exceeded limit
+        rule: usize,
+        /// Line of rule in scheme file
+        line: usize,
+    },
+    /// Rule could not be compiled or matched, so validity is not known
+    MatchError {
+        /// Index of rule which failed
+        rule: usize,
+        /// Line of rule in scheme file
+        line: usize,
+        /// Message of regex error
+        err: String,
+    },
     /// Custom reason for rule
-    Custom(String),
+    Custom {
+        /// Text of reason
+        reason: String,
+        /// Index of reason in `Phonet::reasons`
+        key: usize,
+        /// Index of rule which made word invalid
+        rule: usize,
+        /// Byte span of match of negative rule in word
+        ///
+        /// `None` for positive rule, which did not match
+        span: Option<Range<usize>>,
+    },
 }
 
 impl FailReason {
+    /// Get text of reason, without formatting
+    pub fn text(&self) -> &str {
+        match self {
+            Passed => "",
+            ShouldBeInvalid => "Valid, but should be invalid",
+            FailReason::Timeout { .. } => "Rule exceeded backtrack limit",
+            FailReason::MatchError { err, .. } => err,
+            NoReasonGiven { .. } => "No reason given",
+            Custom { reason, .. } => reason,
+        }
+    }
+
+    /// Get index of rule which made word invalid, if any
+    pub fn rule(&self) -> Option<usize> {
+        match self {
+            Passed | ShouldBeInvalid => None,
+            NoReasonGiven { rule, .. }
+            | Custom { rule, .. }
+            | FailReason::Timeout { rule, .. }
+            | FailReason::MatchError { rule, .. } => Some(*rule),
+        }
+    }
+
+    /// Get byte span of match of negative rule which made word invalid, if any
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Passed
+            | ShouldBeInvalid
+            | FailReason::Timeout { .. }
+            | FailReason::MatchError { .. } => None,
+            NoReasonGiven { span, .. } | Custom { span, .. } => span.clone(),
+        }
+    }
+
     fn from(validity: ValidStatus, reasons: &[String]) -> Self {
         match validity {
             // Test was valid, but it should have been invalid
             Valid => ShouldBeInvalid,
 
+            // Validity is not known
+            ValidStatus::Timeout { rule, line } => FailReason::Timeout { rule, line },
+            ValidStatus::MatchError { rule, line, err } => {
+                FailReason::MatchError { rule, line, err }
+            }
+
             // Test was invalid, but it should have been valid
-            Invalid(reason) => match reason {
-                // No reason was given for rule
-                None => NoReasonGiven,
-
-                // Find rule reason in scheme
-                Some(reason) => match reasons.get(reason) {
-                    // Rule found - Custom reason
-                    Some(x) => FailReason::Custom(x.to_string()),
-                    // No rule found
-                    // ? this should not happen ever ?
-                    None => NoReasonGiven,
+            Invalid {
+                reason_ref,
+                rule,
+                span,
+            } => match reason_ref.and_then(|key| Some((key, reasons.get(key)?))) {
+                // Find rule reason in scheme - Custom reason
+                Some((key, reason)) => Custom {
+                    reason: reason.to_string(),
+                    key,
+                    rule,
+                    span,
                 },
+
+                // No reason was given for rule
+                None => NoReasonGiven { rule, span },
             },
         }
     }
@@ -262,12 +843,38 @@ impl FailReason {
 
 /// State of rules match of word
 ///
-/// If invalid, reason reference can be provided
+/// If invalid, index of rule, reason reference, and span of match are provided
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ValidStatus {
     /// String matches
     Valid,
     /// String does not match
-    Invalid(Option<usize>),
+    Invalid {
+        /// Reference to reason of rule
+        reason_ref: Option<usize>,
+        /// Index of rule which did not match
+        rule: usize,
+        /// Byte span of match of negative rule in word
+        ///
+        /// `None` for positive rule, which did not match
+        span: Option<Range<usize>>,
+    },
+    /// Rule exceeded backtrack limit while matching string, so validity is not known
+    Timeout {
+        /// Index of rule which exceeded limit
+        rule: usize,
+        /// Line of rule in scheme file
+        line: usize,
+    },
+    /// Rule could not be compiled or matched, so validity is not known
+    MatchError {
+        /// Index of rule which failed
+        rule: usize,
+        /// Line of rule in scheme file
+        line: usize,
+        /// Message of regex error
+        err: String,
+    },
 }
 
 impl ValidStatus {
@@ -278,26 +885,162 @@ impl ValidStatus {
         }
         false
     }
+
+    /// Get index of rule which made word invalid, if any
+    pub fn rule(&self) -> Option<usize> {
+        match self {
+            Valid => None,
+            Invalid { rule, .. }
+            | ValidStatus::Timeout { rule, .. }
+            | ValidStatus::MatchError { rule, .. } => Some(*rule),
+        }
+    }
+
+    /// Get byte span of match of negative rule which made word invalid, if any
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Valid | ValidStatus::Timeout { .. } | ValidStatus::MatchError { .. } => None,
+            Invalid { span, .. } => span.clone(),
+        }
+    }
+}
+
+/// Check if string is valid with only one rule, ignoring other rules
+///
+/// Index of rule in `ValidStatus` is index in `rules`
+fn validate_with_rule(word: &str, rules: &[Rule], index: usize) -> ValidStatus {
+    match rules.get(index) {
+        Some(rule) => match validate_test(word, std::slice::from_ref(rule)) {
+            Valid => Valid,
+            Invalid {
+                reason_ref, span, ..
+            } => Invalid {
+                reason_ref,
+                rule: index,
+                span,
+            },
+            ValidStatus::Timeout { line, .. } => ValidStatus::Timeout { rule: index, line },
+            ValidStatus::MatchError { line, err, .. } => ValidStatus::MatchError {
+                rule: index,
+                line,
+                err,
+            },
+        },
+        None => Valid,
+    }
 }
 
 /// Check if string is valid with rules
-pub fn validate_test(word: &str, rules: &Vec<Rule>) -> ValidStatus {
+pub fn validate_test(word: &str, rules: &[Rule]) -> ValidStatus {
     // Check for match with every rule, if not, return reason
-    for Rule {
-        intent,
-        pattern,
-        reason_ref,
-    } in rules
-    {
+    for (index, rule) in rules.iter().enumerate() {
+        // Custom validator, instead of pattern
+        // Match is the whole word
+        if let Some(validator) = &rule.validator {
+            let Some(found) = validator.is_match(word) else {
+                return ValidStatus::MatchError {
+                    rule: index,
+                    line: rule.line,
+                    err: validator.unregistered_message(),
+                };
+            };
+            if rule.intent ^ found {
+                return Invalid {
+                    reason_ref: rule.reason_ref,
+                    rule: index,
+                    span: found.then_some(0..word.len()),
+                };
+            }
+            continue;
+        }
+
+        // Compile regex, if not yet compiled
+        let pattern = match rule.regex() {
+            Ok(pattern) => pattern,
+            Err(err) => {
+                return ValidStatus::MatchError {
+                    rule: index,
+                    line: rule.line,
+                    err: err.to_string(),
+                };
+            }
+        };
+
         // Check if rule matches, and whether match signifies returning invalid or continuing
-        if intent
-            ^ pattern
-                .is_match(word)
-                .expect("Failed checking regex match. This error should NEVER APPEAR!")
-        {
-            return Invalid(*reason_ref);
+        let found = match pattern.find(word) {
+            Ok(found) => found,
+
+            // Stop rule which would take too long
+            Err(fancy_regex::Error::RuntimeError(RuntimeError::BacktrackLimitExceeded)) => {
+                return ValidStatus::Timeout {
+                    rule: index,
+                    line: rule.line,
+                };
+            }
+
+            Err(err) => {
+                return ValidStatus::MatchError {
+                    rule: index,
+                    line: rule.line,
+                    err: err.to_string(),
+                };
+            }
+        };
+        if rule.intent ^ found.is_some() {
+            return Invalid {
+                reason_ref: rule.reason_ref,
+                rule: index,
+                span: found.map(|found| found.start()..found.end()),
+            };
         }
     }
 
     Valid
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_duration_works() {
+        assert_eq!(format_duration(Duration::from_millis(1_234)), "1.2s");
+        assert_eq!(format_duration(Duration::from_millis(340)), "340ms");
+        assert_eq!(format_duration(Duration::from_micros(85)), "85µs");
+        assert_eq!(format_duration(Duration::ZERO), "0µs");
+    }
+
+    #[test]
+    fn format_note_works() {
+        assert_eq!(format_note(&Note::new("Some note"), true), "Some note");
+        assert_eq!(format_note(&Note::new("Some *note*"), true), "Some *note*");
+        assert_eq!(
+            format_note(&Note::new("== Header =="), true),
+            "\n== Header =="
+        );
+        assert_eq!(format_note(&Note::new("---"), true), "-".repeat(40));
+
+        assert_eq!(
+            format_note(&Note::new("Some note"), false),
+            "\x1b[34mSome note\x1b[0m"
+        );
+        assert_eq!(
+            format_note(&Note::new("== Header =="), false),
+            "\n\x1b[1;4;34mHeader\x1b[0m"
+        );
+        assert_eq!(
+            format_note(&Note::new("*some* **note**"), false),
+            "\x1b[34m\x1b[3msome\x1b[23m \x1b[1mnote\x1b[22m\x1b[0m"
+        );
+        assert_eq!(
+            format_note(&Note::new("* not emphasis *"), false),
+            "\x1b[34m* not emphasis *\x1b[0m"
+        );
+        assert_eq!(format_note(&Note::new("=="), false), "\x1b[34m==\x1b[0m");
+        assert_eq!(format_note(&Note::new("! Some note"), true), "! Some note");
+        assert_eq!(
+            format_note(&Note::new("!Some note"), false),
+            "\x1b[33;1m! \x1b[0;33mSome note\x1b[0m"
+        );
+    }
+}
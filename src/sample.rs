@@ -0,0 +1,103 @@
+// * This file is just for the binary
+
+use phonet::TestDefinition;
+use rand::{
+    rngs::StdRng,
+    seq::{index, SliceRandom},
+    SeedableRng,
+};
+
+/// Only keep a random subset of tests, in order of definition
+///
+/// Notes are removed. If `count` is not less than amount of tests, all tests are kept
+///
+/// Returns amount of tests before sampling
+pub fn sample_tests(tests: &mut Vec<TestDefinition>, count: usize, seed: u64) -> usize {
+    let all: Vec<TestDefinition> = tests
+        .drain(..)
+        .filter(|test| matches!(test, TestDefinition::Test { .. }))
+        .collect();
+    let total = all.len();
+
+    if count >= total {
+        *tests = all;
+        return total;
+    }
+
+    // Indices of chosen tests, sorted to keep order
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut chosen = index::sample(&mut rng, total, count).into_vec();
+    chosen.sort_unstable();
+
+    *tests = chosen.into_iter().map(|index| all[index].clone()).collect();
+    total
+}
+
+/// Shuffle order of tests and notes
+pub fn shuffle_tests(tests: &mut [TestDefinition], seed: u64) {
+    tests.shuffle(&mut StdRng::seed_from_u64(seed));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phonet::Note;
+
+    fn words(tests: &[TestDefinition]) -> Vec<&str> {
+        tests
+            .iter()
+            .filter_map(|test| match test {
+                TestDefinition::Test { word, .. } => Some(word.as_str()),
+                TestDefinition::Note(_) => None,
+            })
+            .collect()
+    }
+
+    fn make_tests() -> Vec<TestDefinition> {
+        let mut tests = vec![TestDefinition::Note(Note::new("Note"))];
+        tests.extend((0..20).map(|i| TestDefinition::Test {
+            intent: true,
+            word: format!("{i:02}"),
+            label: None,
+            target: None,
+            line: None,
+        }));
+        tests
+    }
+
+    #[test]
+    fn sample_tests_works() {
+        let mut tests = make_tests();
+        assert_eq!(sample_tests(&mut tests, 5, 42), 20);
+        assert_eq!(tests.len(), 5);
+
+        // Order is kept
+        let sampled = words(&tests);
+        let mut sorted = sampled.clone();
+        sorted.sort();
+        assert_eq!(sampled, sorted);
+
+        // Same seed gives same sample
+        let mut again = make_tests();
+        sample_tests(&mut again, 5, 42);
+        assert_eq!(words(&again), sampled);
+
+        // Not enough tests - Keep all, without notes
+        let mut tests = make_tests();
+        sample_tests(&mut tests, 100, 42);
+        assert_eq!(tests.len(), 20);
+    }
+
+    #[test]
+    fn shuffle_tests_works() {
+        let mut tests = make_tests();
+        shuffle_tests(&mut tests, 7);
+        assert_eq!(tests.len(), 21);
+
+        let mut again = make_tests();
+        shuffle_tests(&mut again, 7);
+        assert_eq!(words(&again), words(&tests));
+
+        assert_ne!(words(&tests), words(&make_tests()));
+    }
+}
@@ -0,0 +1,195 @@
+use fancy_regex::Regex;
+
+use crate::Phonet;
+
+/// Length of words generated by self-test
+const GENERATE_LENGTH: std::ops::Range<usize> = 3..14;
+
+/// Results of consistency checks of a scheme
+///
+/// Create with `Phonet::self_test()`
+pub struct SelfTest {
+    /// Result of each check, in order
+    pub checks: Vec<SelfTestCheck>,
+}
+
+/// Result of a single consistency check
+pub struct SelfTestCheck {
+    /// Description of check
+    pub name: &'static str,
+    /// Whether check passed, failed, or was skipped
+    pub status: CheckStatus,
+}
+
+/// Status of a single consistency check
+#[derive(Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// No problems were found
+    Passed,
+    /// Problems were found, with message of each problem
+    Failed(Vec<String>),
+    /// Check does not apply to scheme, with reason
+    Skipped(&'static str),
+}
+
+impl Phonet {
+    /// Check that scheme is consistent, without running tests
+    ///
+    /// - Every class compiles as a regex, with other classes substituted
+    /// - Every rule compiles as a regex
+    /// - Every reason is used by some rule
+    /// - A valid word can be generated, if the 'any' class (`$_`) is defined
+    pub fn self_test(&self) -> SelfTest {
+        SelfTest {
+            checks: vec![
+                SelfTestCheck {
+                    name: "Every class compiles",
+                    status: self.check_classes(),
+                },
+                SelfTestCheck {
+                    name: "Every rule compiles",
+                    status: self.check_rules(),
+                },
+                SelfTestCheck {
+                    name: "Every reason is used by a rule",
+                    status: self.check_reasons(),
+                },
+                SelfTestCheck {
+                    name: "A valid word can be generated",
+                    status: self.check_generate(),
+                },
+            ],
+        }
+    }
+
+    /// Check that every class compiles as a regex, with other classes substituted
+    fn check_classes(&self) -> CheckStatus {
+        // Sort by name, for consistent order
+        let mut classes: Vec<_> = self.classes.iter().collect();
+        classes.sort();
+
+        CheckStatus::from_problems(
+            classes
+                .into_iter()
+                .filter_map(|(name, value)| {
+                    let err = Regex::new(value).err()?;
+                    Some(format!("Class `{name}`: {err}"))
+                })
+                .collect(),
+        )
+    }
+
+    /// Check that every rule compiles as a regex
+    fn check_rules(&self) -> CheckStatus {
+        CheckStatus::from_problems(
+            self.rules
+                .iter()
+                .filter_map(|rule| Some(rule.regex().err()?.to_string()))
+                .collect(),
+        )
+    }
+
+    /// Check that every reason is used by some rule
+    fn check_reasons(&self) -> CheckStatus {
+        CheckStatus::from_problems(
+            self.reasons
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| {
+                    !self
+                        .rules
+                        .iter()
+                        .any(|rule| rule.reason_ref == Some(*index))
+                })
+                .map(|(_, reason)| format!("Reason '{reason}' is not used by any rule"))
+                .collect(),
+        )
+    }
+
+    /// Check that a valid word can be generated, if the 'any' class is defined
+    fn check_generate(&self) -> CheckStatus {
+        if !self.classes.contains_key("_") {
+            return CheckStatus::Skipped("No 'any' class (`$_`) is defined");
+        }
+
+        match self.generate(1, GENERATE_LENGTH) {
+            Ok(_) => CheckStatus::Passed,
+            Err(err) => CheckStatus::Failed(vec![err.to_string()]),
+        }
+    }
+}
+
+impl CheckStatus {
+    /// Returns `Passed` if there are no problems, otherwise `Failed`
+    fn from_problems(problems: Vec<String>) -> Self {
+        if problems.is_empty() {
+            Self::Passed
+        } else {
+            Self::Failed(problems)
+        }
+    }
+}
+
+impl SelfTest {
+    /// Returns `true` if no check failed
+    pub fn passed(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|check| matches!(check.status, CheckStatus::Failed(_)))
+    }
+
+    /// Display result of each check to standard output
+    ///
+    /// This can be implemented manually
+    pub fn display(&self, no_color: bool) {
+        for check in &self.checks {
+            let (result, color) = match check.status {
+                CheckStatus::Passed => ("pass", "32"),
+                CheckStatus::Failed(_) => ("FAIL", "31"),
+                CheckStatus::Skipped(_) => ("skip", "33"),
+            };
+
+            if no_color {
+                println!(" {result}  {}", check.name);
+            } else {
+                println!("  \x1b[1;{color}m{result}\x1b[0m  {}", check.name);
+            }
+
+            match &check.status {
+                CheckStatus::Failed(problems) => {
+                    for problem in problems {
+                        if no_color {
+                            println!("        {problem}");
+                        } else {
+                            println!("        \x1b[31m{problem}\x1b[0m");
+                        }
+                    }
+                }
+
+                CheckStatus::Skipped(reason) => {
+                    if no_color {
+                        println!("        {reason}");
+                    } else {
+                        println!("        \x1b[2m{reason}\x1b[0m");
+                    }
+                }
+
+                CheckStatus::Passed => (),
+            }
+        }
+
+        // Final print
+        if self.passed() {
+            if no_color {
+                println!("Self-test passed!");
+            } else {
+                println!("\x1b[32;1;3mSelf-test passed!\x1b[0m");
+            }
+        } else if no_color {
+            println!("Self-test failed!");
+        } else {
+            println!("\x1b[31;1;3mSelf-test failed!\x1b[0m");
+        }
+    }
+}
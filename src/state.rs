@@ -0,0 +1,152 @@
+// * This file is just for the binary
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use phonet::{Results, TestDefinition, TestResult};
+use serde_json::{json, Value};
+
+/// End of name of state file, saved next to the scheme file
+const STATE_SUFFIX: &str = "phonet-state.json";
+
+/// Get path of state file, for a scheme file
+///
+/// Name of state file includes name of scheme file, so schemes in the same directory have separate states
+///
+/// Eg. `examples/example.phonet` -> `examples/.example.phonet-state.json`
+pub fn state_path(file: &str) -> PathBuf {
+    let path = PathBuf::from(file);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    path.with_file_name(format!(".{stem}.{STATE_SUFFIX}"))
+}
+
+/// Read intent and word of each failed test from state file
+///
+/// Returns empty list if file does not exist, or is not valid
+pub fn read_failed(path: &Path) -> Vec<(bool, String)> {
+    let Ok(file) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let Ok(state) = serde_json::from_str::<Value>(&file) else {
+        return Vec::new();
+    };
+
+    state["failed"]
+        .as_array()
+        .map(|failed| {
+            failed
+                .iter()
+                .filter_map(|test| {
+                    Some((
+                        test["intent"].as_bool()?,
+                        test["word"].as_str()?.to_string(),
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Only keep tests which failed in the last run
+///
+/// Notes are removed. If none of the failed tests still exist, all tests are kept
+pub fn filter_failed(tests: &mut Vec<TestDefinition>, failed: &[(bool, String)]) {
+    let filtered: Vec<TestDefinition> = tests
+        .iter()
+        .filter(|test| match test {
+            TestDefinition::Test { intent, word, .. } => failed
+                .iter()
+                .any(|(failed_intent, failed_word)| failed_intent == intent && failed_word == word),
+            TestDefinition::Note(_) => false,
+        })
+        .cloned()
+        .collect();
+
+    if !filtered.is_empty() {
+        *tests = filtered;
+    }
+}
+
+/// Save failed tests to state file, or remove state file if all tests passed
+pub fn write_failed(path: &Path, results: &Results) -> io::Result<()> {
+    let failed: Vec<Value> = results
+        .list
+        .iter()
+        .filter_map(|result| match result {
+            TestResult::Test {
+                intent,
+                word,
+                pass: false,
+                ..
+            } => Some(json!({ "intent": intent, "word": word })),
+            _ => None,
+        })
+        .collect();
+
+    // All passed - Next run uses all tests
+    if failed.is_empty() {
+        return match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        };
+    }
+
+    fs::write(path, json!({ "failed": failed }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phonet::Note;
+
+    #[test]
+    fn state_path_works() {
+        assert_eq!(
+            state_path("phonet"),
+            PathBuf::from(".phonet.phonet-state.json")
+        );
+        assert_eq!(
+            state_path("examples/example.phonet"),
+            PathBuf::from("examples/.example.phonet-state.json")
+        );
+        // Schemes in same directory
+        assert_ne!(
+            state_path("examples/example.phonet"),
+            state_path("examples/other.phonet")
+        );
+    }
+
+    #[test]
+    fn filter_failed_works() {
+        let test = |intent, word: &str| TestDefinition::Test {
+            intent,
+            word: word.to_string(),
+            label: None,
+            target: None,
+            line: None,
+        };
+
+        let mut tests = vec![
+            TestDefinition::Note(Note::new("Note")),
+            test(true, "a"),
+            test(false, "a"),
+            test(true, "b"),
+        ];
+        filter_failed(&mut tests, &[(false, "a".to_string())]);
+        assert_eq!(tests.len(), 1);
+        assert!(
+            matches!(&tests[0], TestDefinition::Test { intent: false, word, .. } if word == "a")
+        );
+
+        // No failed tests exist - Keep all
+        let mut tests = vec![test(true, "a"), test(true, "b")];
+        filter_failed(&mut tests, &[(true, "c".to_string())]);
+        assert_eq!(tests.len(), 2);
+    }
+}
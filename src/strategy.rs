@@ -0,0 +1,62 @@
+use std::ops::Range;
+
+use proptest::{collection::vec, sample::select, strategy::Strategy};
+
+use crate::{run::validate_test, types::Error, Phonet};
+
+impl Phonet {
+    /// Create a `proptest` strategy, which only yields random words that fit the rules
+    ///
+    /// Words are built from letters of the 'any' class (`$_`), as with `Phonet::generate`.
+    /// Fails if the 'any' class is missing, or has no letters
+    ///
+    /// Requires the `proptest` feature
+    pub fn word_strategy(
+        &self,
+        length: Range<usize>,
+    ) -> Result<impl Strategy<Value = String>, Error> {
+        let letters = self.any_letters()?;
+        if letters.is_empty() {
+            return Err(Error::EmptyAnyClass);
+        }
+        let rules = self.rules.clone();
+
+        Ok(vec(select(letters), length)
+            .prop_map(|chars| chars.into_iter().collect::<String>())
+            .prop_filter("Word must fit the rules", move |word| {
+                validate_test(word, &rules).is_valid()
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::{prop_assert, test_runner::TestRunner};
+
+    use super::*;
+
+    #[test]
+    fn word_strategy_yields_valid_words() {
+        let scheme = Phonet::parse("$_ = [ptkaeiou]; $C = [ptk]; $V = [aeiou]; + ^(<C><V>)+$")
+            .expect("Failed to parse");
+        let strategy = scheme
+            .word_strategy(2..8)
+            .expect("Failed to create strategy");
+
+        TestRunner::default()
+            .run(&strategy, |word| {
+                prop_assert!(validate_test(&word, &scheme.rules).is_valid());
+                Ok(())
+            })
+            .expect("Strategy yielded an invalid word");
+    }
+
+    #[test]
+    fn word_strategy_empty_any_class() {
+        let scheme = Phonet::parse("$_ = [()]; + ^a").expect("Failed to parse");
+        assert!(matches!(
+            scheme.word_strategy(2..8),
+            Err(Error::EmptyAnyClass)
+        ));
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    run::{validate_test, ValidStatus},
+    Phonet,
+};
+
+/// Counts of words from `Phonet::validate_stream`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StreamCounts {
+    /// Amount of valid words
+    pub valid: usize,
+    /// Amount of invalid words
+    pub invalid: usize,
+}
+
+impl Phonet {
+    /// Validate each line of reader as a word, and write result of each word to writer
+    ///
+    /// Lines are read and written one at a time, so only counts are kept in memory
+    ///
+    /// Each result is written as tab-separated values: `word`, `valid` or `invalid`, and the reason if invalid
+    ///
+    /// Blank lines are skipped
+    pub fn validate_stream(
+        &self,
+        reader: impl BufRead,
+        mut writer: impl Write,
+    ) -> io::Result<StreamCounts> {
+        let mut counts = StreamCounts::default();
+
+        for line in reader.lines() {
+            let line = line?;
+            let word = line.trim();
+
+            // Skip blank line
+            if word.is_empty() {
+                continue;
+            }
+
+            match validate_test(&self.strip_diacritics(word), &self.rules) {
+                ValidStatus::Valid => {
+                    counts.valid += 1;
+                    writeln!(writer, "{word}\tvalid")?;
+                }
+
+                ValidStatus::Invalid { reason_ref, .. } => {
+                    counts.invalid += 1;
+
+                    let reason = reason_ref
+                        .and_then(|reason| self.reasons.get(reason))
+                        .map(String::as_str)
+                        .unwrap_or("No reason given");
+
+                    writeln!(writer, "{word}\tinvalid\t{reason}")?;
+                }
+
+                ValidStatus::Timeout { line, .. } => {
+                    counts.invalid += 1;
+                    writeln!(
+                        writer,
+                        "{word}\tinvalid\tRule at line {line} exceeded backtrack limit"
+                    )?;
+                }
+
+                ValidStatus::MatchError { err, .. } => {
+                    counts.invalid += 1;
+                    writeln!(writer, "{word}\tinvalid\t{err}")?;
+                }
+            }
+        }
+
+        writer.flush()?;
+        Ok(counts)
+    }
+}
@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+use crate::{types::TestDefinition, Phonet};
+
+impl Phonet {
+    /// Replace text of each reason with its translation, using original text as key
+    ///
+    /// Notes are also replaced, if they have a translation
+    ///
+    /// Returns original text of each reason which has no translation, without duplicates
+    pub fn translate(&mut self, translations: &HashMap<String, String>) -> Vec<String> {
+        let mut missing: Vec<String> = Vec::new();
+
+        for reason in &mut self.reasons {
+            match translations.get(reason.as_str()) {
+                Some(translation) => *reason = translation.to_string(),
+                None => {
+                    if !missing.contains(reason) {
+                        missing.push(reason.to_string());
+                    }
+                }
+            }
+        }
+
+        for test in &mut self.tests {
+            if let TestDefinition::Note(note) = test {
+                if let Some(translation) = translations.get(note.text.as_str()) {
+                    note.text = translation.to_string();
+                }
+            }
+        }
+
+        missing
+    }
+}
@@ -1,5 +1,9 @@
 use snafu::prelude::*;
 
+use super::Warning;
+
+use Error::*;
+
 /// Error enum for `Phonet` struct in `parse.rs`
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -8,9 +12,23 @@ pub enum Error {
     ))]
     UnknownIntentIdentifier { ch: char, line: usize },
 
+    #[snafu(display("Test label is missing closing bracket `]`, at line {line}"))]
+    UnclosedTestLabel { line: usize },
+
+    #[snafu(display("Test with `^` must be after a rule, to test with, at line {line}"))]
+    NoTargetRule { line: usize },
+
     #[snafu(display("Unknown line operator `{ch}`, at line {line}"))]
     UnknownLineOperator { ch: char, line: usize },
 
+    #[snafu(display(
+        "Unknown directive `{name}`. Must be either `strip` or `preserve`, at line {line}"
+    ))]
+    UnknownDirective { name: String, line: usize },
+
+    #[snafu(display("Directive `preserve` must have at least one character, at line {line}"))]
+    EmptyDirective { line: usize },
+
     #[snafu(display("Mode already defined, at line {line}"))]
     ModeAlreadyDefined { line: usize },
 
@@ -25,14 +43,30 @@ pub enum Error {
     ))]
     InvalidClassName { name: String, line: usize },
 
-    #[snafu(display("Class already exists with `{name}`, on {line}"))]
-    ClassAlreadyExist { name: String, line: usize },
-
     #[snafu(display("No class value given, with name `{name}`, at line {line}"))]
     NoClassValue { name: String, line: usize },
 
-    #[snafu(display("Failed to parse Regex: {err}, at line {line}"))]
+    #[snafu(display(
+        "Invalid phoneme. Must be written as `/symbol/ = [+feature, -feature]`, at line {line}"
+    ))]
+    InvalidPhoneme { line: usize },
+
+    #[snafu(display("Feature `{feature}` is not used by any phoneme, at line {line}"))]
+    UnknownFeature { feature: String, line: usize },
+
+    #[snafu(display("No phonemes have the features of class `{name}`, at line {line}"))]
+    NoFeatureMatch { name: String, line: usize },
+
+    #[snafu(display("Invalid regex in value of class `{name}`: {err}, at line {line}"))]
+    ClassRegexFail {
+        name: String,
+        err: fancy_regex::Error,
+        line: usize,
+    },
+
+    #[snafu(display("Failed to parse Regex of rule `{rule}`: {err}, at line {line}"))]
     RegexFail {
+        rule: String,
         err: fancy_regex::Error,
         line: usize,
     },
@@ -55,6 +89,147 @@ pub enum Error {
   ))]
     ClassUnexpectedEnd { pattern: String, line: usize },
 
+    #[snafu(display(
+        "Environment can only have one slash (`/`), in pattern `{pattern}`, at line {line}"
+    ))]
+    EnvironmentMultipleSlash { pattern: String, line: usize },
+
+    #[snafu(display(
+        "Environment must have exactly one focus (`_`), in pattern `{pattern}`, at line {line}"
+    ))]
+    EnvironmentFocus { pattern: String, line: usize },
+
+    #[snafu(display(
+        "Position qualifier `{qualifier}:` uses the `V` class for vowels, which is not defined, at line {line}"
+    ))]
+    QualifierWithoutVowels { qualifier: String, line: usize },
+
+    #[snafu(display("Warning denied: {warning}"))]
+    DeniedWarning { warning: Warning },
+
+    #[snafu(display(
+        "Invalid unicode escape `{escape}`. Must be hexadecimal code point in braces, like `\\u{{0303}}`, at line {line}"
+    ))]
+    InvalidUnicodeEscape { escape: String, line: usize },
+
+    #[snafu(display(
+        "Invalid repetition `{repetition}` of class. Must be like `{{2}}`, `{{2,}}` or `{{1,3}}`, with minimum not greater than maximum, at line {line}"
+    ))]
+    InvalidRepetition { repetition: String, line: usize },
+
+    #[snafu(display(
+        "Class `{name}` is optional or repeated at line {line}, but can match no letters. Optional and repeated classes must match whole phonemes"
+    ))]
+    EmptyQuantifiedClass { name: String, line: usize },
+
+    #[snafu(display("Scheme is too long ({len} bytes). Maximum is {max} bytes"))]
+    SchemeTooLong { len: usize, max: usize },
+
+    #[snafu(display("Statement is too long. Maximum is {max} bytes, at line {line}"))]
+    StatementTooLong { max: usize, line: usize },
+
+    #[snafu(display(
+        "Class `{name}` is nested more than {max} classes deep. Classes might use each other in a loop, at line {line}"
+    ))]
+    ClassTooDeep {
+        name: String,
+        max: usize,
+        line: usize,
+    },
+
+    #[snafu(display(
+        "Pattern is too long after classes are substituted. Maximum is {max} bytes, at line {line}"
+    ))]
+    PatternTooLong { max: usize, line: usize },
+
+    #[snafu(display("Extra rule `{rule}` must start with `+` or `!`"))]
+    InvalidExtraRule { rule: String },
+
+    #[snafu(display(
+        "Class or phoneme `{name}` is defined in both schemes, and cannot be merged"
+    ))]
+    MergeConflict { name: String },
+
     #[snafu(display("No 'any' class was defined. Define with `$_ = ...`"))]
     MissingAnyClass,
+
+    #[snafu(display("'Any' class (`$_`) has no letters to create words from"))]
+    EmptyAnyClass,
+
+    #[snafu(display(
+        "Minimum length ({min}) of generated words must be less than maximum length ({max})"
+    ))]
+    EmptyGenerateLength { min: usize, max: usize },
+
+    #[snafu(display(
+        "Could not generate a valid word after {attempts} attempts. Scheme might be too constrained. Rejections by rule: {rejections}"
+    ))]
+    GenerationFailed { attempts: usize, rejections: String },
+
+    #[snafu(display("No valid words of positive tests (`?+`) to generate weighted words from"))]
+    EmptyCorpus,
+
+    #[snafu(display("Could not read file `{path}`: {source}"))]
+    ReadFile {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display(
+        "File is not valid {encoding}, at byte {position}. Save the file as UTF-8 or UTF-16"
+    ))]
+    InvalidEncoding {
+        encoding: &'static str,
+        position: usize,
+    },
+}
+
+impl Error {
+    /// Get line of scheme file which caused error, if any
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            UnknownIntentIdentifier { line, .. }
+            | UnclosedTestLabel { line, .. }
+            | NoTargetRule { line, .. }
+            | UnknownLineOperator { line, .. }
+            | UnknownDirective { line, .. }
+            | EmptyDirective { line, .. }
+            | ModeAlreadyDefined { line, .. }
+            | InvalidMode { line, .. }
+            | NoClassName { line, .. }
+            | InvalidClassName { line, .. }
+            | NoClassValue { line, .. }
+            | InvalidPhoneme { line, .. }
+            | UnknownFeature { line, .. }
+            | NoFeatureMatch { line, .. }
+            | ClassRegexFail { line, .. }
+            | RegexFail { line, .. }
+            | ClassNotFound { line, .. }
+            | ClassUnexpectedOpenName { line, .. }
+            | ClassUnexpectedCloseName { line, .. }
+            | ClassUnexpectedEnd { line, .. }
+            | EnvironmentMultipleSlash { line, .. }
+            | EnvironmentFocus { line, .. }
+            | QualifierWithoutVowels { line, .. }
+            | InvalidUnicodeEscape { line, .. }
+            | InvalidRepetition { line, .. }
+            | EmptyQuantifiedClass { line, .. }
+            | StatementTooLong { line, .. }
+            | ClassTooDeep { line, .. }
+            | PatternTooLong { line, .. } => Some(*line),
+
+            DeniedWarning { warning } => Some(warning.line()),
+
+            SchemeTooLong { .. }
+            | InvalidExtraRule { .. }
+            | MergeConflict { .. }
+            | MissingAnyClass
+            | EmptyAnyClass
+            | EmptyGenerateLength { .. }
+            | GenerationFailed { .. }
+            | EmptyCorpus
+            | ReadFile { .. }
+            | InvalidEncoding { .. } => None,
+        }
+    }
 }
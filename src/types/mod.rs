@@ -1,54 +1,317 @@
 /// Holds error type
 mod error;
+/// Holds warning type
+mod warning;
 
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
 use clap::{builder::PossibleValue, ValueEnum};
-use fancy_regex::Regex;
+use fancy_regex::{Regex, RegexBuilder};
+use once_cell::sync::OnceCell;
 
 pub use error::Error;
+pub use warning::Warning;
 pub use crate::run::FailReason;
 
 use DisplayLevel::*;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Rule {
     pub intent: bool,
-    pub pattern: Regex,
+    /// Pattern of rule, with classes substituted
+    pub pattern: String,
+    /// Pattern of rule as written in scheme file, before classes are substituted
+    pub source: String,
+    /// Pattern of rule before classes are substituted, with shorthand syntax converted
+    pub template: String,
     pub reason_ref: Option<usize>,
+    /// Line of rule in scheme file
+    pub line: usize,
+    /// Names of classes used in rule, including classes used by those classes
+    pub classes: Vec<String>,
+    /// Maximum amount of backtracking steps for each match, if not the default of `fancy-regex`
+    pub backtrack_limit: Option<usize>,
+    /// Custom validator, used instead of pattern, for rule written as `%name`
+    pub validator: Option<Validator>,
+    /// Compiled regex of pattern, created on first use
+    regex: OnceCell<Regex>,
+}
+
+/// Function to check a word, for rules with a custom validator
+///
+/// Returns `true` if word matches, like the pattern of a rule
+pub type ValidatorFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Custom validator of rule, written as `%name` in scheme file
+///
+/// Register function with `Phonet::register_validator`
+#[derive(Clone)]
+pub struct Validator {
+    /// Name of validator, as written after `%`
+    pub name: String,
+    /// Function to check word, if registered
+    pub function: Option<ValidatorFn>,
+}
+
+impl Validator {
+    /// Check if word matches, with registered function
+    ///
+    /// Returns `None` if no function is registered
+    pub fn is_match(&self, word: &str) -> Option<bool> {
+        self.function.as_ref().map(|function| function(word))
+    }
+
+    /// Message for rule with validator which has no function registered
+    pub fn unregistered_message(&self) -> String {
+        format!(
+            "Validator `{}` is not registered. Register it with `Phonet::register_validator`",
+            self.name
+        )
+    }
+}
+
+// Functions do not implement `Debug`
+impl Debug for Validator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Validator")
+            .field("name", &self.name)
+            .field("registered", &self.function.is_some())
+            .finish()
+    }
+}
+
+impl Rule {
+    /// Create rule, without compiling regex
+    ///
+    /// Source and template of rule are the same as pattern, until set
+    pub fn new(intent: bool, pattern: String, reason_ref: Option<usize>, line: usize) -> Self {
+        Rule {
+            intent,
+            source: pattern.clone(),
+            template: pattern.clone(),
+            pattern,
+            reason_ref,
+            line,
+            classes: Vec::new(),
+            backtrack_limit: None,
+            validator: None,
+            regex: OnceCell::new(),
+        }
+    }
+
+    /// Get compiled regex of pattern, compiling it if not yet compiled
+    pub fn regex(&self) -> Result<&Regex, Error> {
+        self.regex.get_or_try_init(|| {
+            let mut builder = RegexBuilder::new(&self.pattern);
+            if let Some(limit) = self.backtrack_limit {
+                builder.backtrack_limit(limit);
+            }
+
+            builder.build().map_err(|err| Error::RegexFail {
+                rule: self.source.clone(),
+                err,
+                line: self.line,
+            })
+        })
+    }
+
+    /// Set pattern of rule, removing compiled regex of old pattern
+    pub(crate) fn set_pattern(&mut self, pattern: String) {
+        self.pattern = pattern;
+        self.regex = OnceCell::new();
+    }
+}
+
+/// Phoneme with distinctive features, defined with `/symbol/ = [+feature, -feature]`
+#[derive(Clone, Debug)]
+pub struct Phoneme {
+    /// Text of phoneme
+    pub symbol: String,
+    /// Value of each feature given
+    pub features: HashMap<String, bool>,
+}
+
+impl Phoneme {
+    /// Create phoneme from symbol and list of features
+    pub fn new(symbol: String, features: Vec<(String, bool)>) -> Self {
+        Phoneme {
+            symbol,
+            features: features.into_iter().collect(),
+        }
+    }
+
+    /// Returns `true` if phoneme has every feature value
+    ///
+    /// A feature which is not given for phoneme counts as minus (`-`)
+    pub fn matches(&self, features: &[(String, bool)]) -> bool {
+        features
+            .iter()
+            .all(|(name, value)| self.features.get(name).copied().unwrap_or(false) == *value)
+    }
+}
+
+/// Diacritics to remove from test words before matching, set with `%` directives
+///
+/// Words are displayed as written
+#[derive(Clone, Debug, Default)]
+pub struct Diacritics {
+    /// Remove every diacritic (combining marks, and modifier letters such as stress and tone marks)
+    pub all: bool,
+    /// Characters to remove
+    pub strip: Vec<char>,
+    /// Characters to keep, even if stripped otherwise
+    pub preserve: Vec<char>,
+}
+
+impl Diacritics {
+    /// Returns `true` if no characters are removed
+    pub fn is_empty(&self) -> bool {
+        !self.all && self.strip.is_empty()
+    }
+
+    /// Returns `true` if character should be removed from words
+    pub fn removes(&self, ch: char) -> bool {
+        !self.preserve.contains(&ch) && (self.strip.contains(&ch) || self.all && is_diacritic(ch))
+    }
+
+    /// Remove diacritics from word
+    pub fn apply(&self, word: &str) -> String {
+        if self.is_empty() {
+            return word.to_string();
+        }
+        word.chars().filter(|ch| !self.removes(*ch)).collect()
+    }
+}
+
+/// Returns `true` if character is a combining mark, or a spacing modifier letter
+///
+/// Eg. `ˈ`, `ˌ`, `˥`, and `\u{0301}`
+fn is_diacritic(ch: char) -> bool {
+    matches!(ch,
+        // Spacing modifier letters (stress, length, tone letters)
+        '\u{02B0}'..='\u{02FF}'
+        // Combining diacritical marks
+        | '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}'
+        // Modifier tone letters
+        | '\u{A700}'..='\u{A71F}'
+    )
 }
 
 /// Alias for hashmap of class name and value
 pub type Classes = HashMap<String, String>;
 
+/// Line of text to display between tests
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Note {
+    /// Text of note, as written, without level marker
+    pub text: String,
+    /// Kind of note, for display and filtering
+    pub level: NoteLevel,
+}
+
+/// Kind of note
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoteLevel {
+    /// Regular note
+    #[default]
+    Info,
+    /// Header, to separate sections, written as `* == Header ==`
+    Heading,
+    /// Important note, written as `*! Note`
+    Warning,
+}
+
+impl Note {
+    /// Create note from text, with level from markers
+    ///
+    /// - `! Note`: Warning (`!` is removed)
+    /// - `== Header ==`: Heading
+    /// - Otherwise: Info
+    pub fn new(text: &str) -> Self {
+        let text = text.trim();
+
+        if let Some(text) = text.strip_prefix('!') {
+            return Self {
+                text: text.trim().to_string(),
+                level: NoteLevel::Warning,
+            };
+        }
+
+        let level = if text.len() > 4 && text.starts_with("==") && text.ends_with("==") {
+            NoteLevel::Heading
+        } else {
+            NoteLevel::Info
+        };
+
+        Self {
+            text: text.to_string(),
+            level,
+        }
+    }
+}
+
+impl Display for Note {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
 /// Definition of test or note
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum TestDefinition {
     /// Display line of text
-    Note(String),
+    Note(Note),
     /// Result of test
     Test {
         /// Intent of test passing
         intent: bool,
         /// Word to test
         word: String,
+        /// Label of test, if any
+        label: Option<String>,
+        /// Index of only rule to test with, if any
+        ///
+        /// Other rules are ignored
+        target: Option<usize>,
+        /// Line of test in scheme file, if any
+        line: Option<usize>,
     },
 }
 
 /// Result of test or note
 pub enum TestResult {
     /// Display line of text
-    Note(String),
+    Note(Note),
     /// Result of test
     Test {
         /// Intent of test passing
         intent: bool,
         /// Word tested
         word: String,
+        /// Label of test, if any
+        label: Option<String>,
         /// Whether test passed or not
         pass: bool,
         /// Reason for fail
         reason: FailReason,
+        /// Index of rule which made word invalid, if any
+        rule: Option<usize>,
+        /// Index of only rule which word was tested with, if any
+        target: Option<usize>,
+        /// Line of test in scheme file, if any
+        line: Option<usize>,
+        /// Time taken to run test
+        duration: Duration,
     },
 }
 
@@ -57,14 +320,105 @@ pub enum TestResult {
 pub enum DisplayLevel {
     /// Show everything (passes, notes, fails)
     ShowAll,
+    /// Show everything, with passes dimmed
+    DimPasses,
     /// Show most (notes, fails), but not passes
     NotesAndFails,
     /// Show only fails, not passes or notes
     JustFails,
+    /// Show only the summary counts, not passes, notes, or fails
+    JustSummary,
     /// Show nothing: not passes, notes, or fails
     HideAll,
 }
 
+/// Options for `Phonet::parse_with` method
+#[derive(Clone, Default)]
+pub struct ParseOptions {
+    /// Compile regex of each rule on first use, instead of while parsing
+    ///
+    /// Invalid rules are not found until used. Use `Phonet::compile_all` to check every rule
+    pub lazy: bool,
+    /// Maximum amount of backtracking steps for each match of a rule, to stop rules which would never finish
+    ///
+    /// A test which exceeds the limit fails, with the rule and its line. Default is `1000000`
+    pub backtrack_limit: Option<usize>,
+    /// Limits on size of scheme, to stop schemes which would be slow to parse
+    pub limits: Limits,
+    /// Classes to define after the scheme, replacing classes with the same name
+    ///
+    /// Written as a class statement, with or without `$`. Eg. `V = aeiouy` or `$V = aeiouy`
+    pub defines: Vec<String>,
+    /// Rules to add after the rules of the scheme
+    ///
+    /// Written as a rule statement. Eg. `+ ^<C>?<V>` or `! <V><V> @ No diphthongs`
+    pub extra_rules: Vec<String>,
+}
+
+/// Limits on size of scheme, for `Phonet::parse_with_limits` method
+///
+/// A scheme which exceeds any limit fails to parse with an error. Use lower limits for untrusted schemes
+#[derive(Clone, Debug)]
+pub struct Limits {
+    /// Maximum length of scheme, in bytes. Default is `10000000`
+    pub max_len: usize,
+    /// Maximum length of a statement, in bytes. Default is `100000`
+    pub max_statement_len: usize,
+    /// Maximum depth of classes used inside other classes. Default is `32`
+    ///
+    /// This also stops classes which use each other in a loop
+    pub max_class_depth: usize,
+    /// Maximum length of a class or rule, after classes are substituted, in bytes. Default is `1000000`
+    pub max_pattern_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_len: 10_000_000,
+            max_statement_len: 100_000,
+            max_class_depth: 32,
+            max_pattern_len: 1_000_000,
+        }
+    }
+}
+
+/// Options for `Phonet::run_with` method
+#[derive(Clone, Default)]
+pub struct RunOptions {
+    /// Amount of threads to run tests on
+    ///
+    /// Results are always in order of definition, regardless of amount of jobs
+    pub jobs: usize,
+    /// Validate every test, instead of reusing validity of repeated words
+    ///
+    /// Repeated words are only validated once on each thread, unless this is set
+    pub no_cache: bool,
+}
+
+/// Options for `Results::display_with` method
+#[derive(Clone, Default)]
+pub struct DisplayOptions {
+    /// Which items to display
+    pub display_level: DisplayLevel,
+    /// Display output in default color
+    pub no_color: bool,
+    /// Display rule which made test fail, as written and with classes substituted
+    pub verbose_failures: bool,
+    /// Name of scheme file, to display with line of failed tests
+    ///
+    /// Eg. `lang.phonet:182`
+    pub filename: Option<String>,
+    /// Mark tests which took at least this long to run, with their duration
+    ///
+    /// Eg. `SLOW 1.2s`
+    pub slow_threshold: Option<Duration>,
+    /// Amount of slowest tests to list after results
+    pub slowest: usize,
+    /// Which notes to display, if display level includes notes
+    pub notes: NoteFilter,
+}
+
 // Custom implementation, for argument aliases
 impl ValueEnum for DisplayLevel {
     fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
@@ -74,6 +428,10 @@ impl ValueEnum for DisplayLevel {
                 .aliases(["s", "show", "sa", "showall"])
                 .help("Show everything (passes, notes, fails)"),
 
+            Self::DimPasses => PossibleValue::new("dim-passes")
+                .aliases(["d", "dim", "dimpasses"])
+                .help("Show everything, with passes dimmed"),
+
             Self::NotesAndFails => PossibleValue::new("notes-and-fails")
                 .aliases(["n", "notesfails", "notes", "na"])
                 .help("Show most (notes, fails), but not passes"),
@@ -82,6 +440,10 @@ impl ValueEnum for DisplayLevel {
                 .aliases(["j", "f", "fails", "justfails"])
                 .help("Show only fails, not passes or notes"),
 
+            Self::JustSummary => PossibleValue::new("just-summary")
+                .aliases(["u", "summary", "sum", "counts", "justsummary"])
+                .help("Show only the summary counts, not passes, notes, or fails"),
+
             Self::HideAll => PossibleValue::new("hide-all")
                 .aliases(["h", "hide", "ha", "hideall"])
                 .help("Show nothing: not passes, notes, or fails"),
@@ -91,19 +453,97 @@ impl ValueEnum for DisplayLevel {
     fn value_variants<'a>() -> &'a [Self] {
         &[
             Self::ShowAll,
+            Self::DimPasses,
             Self::NotesAndFails,
             Self::JustFails,
+            Self::JustSummary,
             Self::HideAll,
         ]
     }
 }
 
+impl DisplayLevel {
+    /// Returns `true` if test or note should be displayed with this level
+    ///
+    /// Summary is not included in this check
+    pub fn includes(&self, item: &TestResult) -> bool {
+        match item {
+            TestResult::Note(_) => matches!(self, ShowAll | DimPasses | NotesAndFails),
+
+            TestResult::Test { pass, .. } => match self {
+                // Always show
+                ShowAll | DimPasses => true,
+                // Only show if failed
+                NotesAndFails | JustFails => !pass,
+                // Never show
+                JustSummary | HideAll => false,
+            },
+        }
+    }
+}
+
 impl Default for DisplayLevel {
     fn default() -> Self {
         ShowAll
     }
 }
 
+/// Setting for controlling which notes are outputted in `PhonetResult::display_with` method
+#[derive(Clone, Copy, Debug, Default)]
+pub enum NoteFilter {
+    /// Show no notes
+    Off,
+    /// Show only headings
+    Headings,
+    /// Show every note
+    #[default]
+    All,
+}
+
+impl NoteFilter {
+    /// Returns `true` if note should be displayed with this filter
+    pub fn includes(&self, note: &Note) -> bool {
+        match self {
+            Self::Off => false,
+            Self::Headings => note.level == NoteLevel::Heading,
+            Self::All => true,
+        }
+    }
+}
+
+// Custom implementation, for argument aliases
+impl ValueEnum for NoteFilter {
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        // `help` values must mirror comments
+        Some(match self {
+            Self::Off => PossibleValue::new("off")
+                .aliases(["o", "none", "hide"])
+                .help("Show no notes"),
+
+            Self::Headings => PossibleValue::new("headings")
+                .aliases(["h", "heading", "headers"])
+                .help("Show only headings"),
+
+            Self::All => PossibleValue::new("all")
+                .aliases(["a", "show"])
+                .help("Show every note"),
+        })
+    }
+
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Off, Self::Headings, Self::All]
+    }
+}
+
+// Parse from name or any alias, ignoring case
+impl FromStr for DisplayLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s.trim(), true)
+    }
+}
+
 impl Display for DisplayLevel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -111,8 +551,10 @@ impl Display for DisplayLevel {
             "{}",
             match self {
                 ShowAll => "ShowAll",
+                DimPasses => "DimPasses",
                 NotesAndFails => "NotesAndFails",
                 JustFails => "JustFails",
+                JustSummary => "JustSummary",
                 HideAll => "HideAll",
             }
         )
@@ -0,0 +1,110 @@
+use snafu::prelude::*;
+
+use Warning::*;
+
+/// Warning enum for `Phonet` struct in `parse.rs`, and `Results` struct in `run.rs`
+///
+/// Unlike `Error`, a warning does not stop parsing or running
+#[derive(Debug, Snafu)]
+pub enum Warning {
+    #[snafu(display(
+        "Class `{name}` is defined again at line {line}, overriding definition at line {first_line}"
+    ))]
+    ClassRedefined {
+        name: String,
+        first_line: usize,
+        line: usize,
+    },
+
+    #[snafu(display(
+        "Phoneme `/{symbol}/` is defined again at line {line}, overriding definition at line {first_line}"
+    ))]
+    PhonemeRedefined {
+        symbol: String,
+        first_line: usize,
+        line: usize,
+    },
+
+    #[snafu(display(
+        "Class `{name}` (defined at line {class_line}) is also used as literal text in rule at line {line}. Use `<{name}>` to reference the class"
+    ))]
+    ClassShadowsLiteral {
+        name: String,
+        class_line: usize,
+        line: usize,
+    },
+
+    #[snafu(display(
+        "Test `{test}` at line {line} is a duplicate of test at line {first_line}. Use `--dedup` to remove duplicates"
+    ))]
+    DuplicateTest {
+        test: String,
+        first_line: usize,
+        line: usize,
+    },
+
+    #[snafu(display(
+        "Rule at line {line} is the same as rule at line {first_line}, so it never changes a result"
+    ))]
+    DuplicateRule { first_line: usize, line: usize },
+
+    #[snafu(display(
+        "Negative rule at line {line} only matches words which also match negative rule at line {first_line}, so it never changes a result"
+    ))]
+    ShadowedRule { first_line: usize, line: usize },
+
+    #[snafu(display(
+        "Rule at line {line} uses reason #{reason}, which does not exist, so failed tests have no reason"
+    ))]
+    UnknownReason { reason: usize, line: usize },
+
+    #[snafu(display(
+        "Test at line {line} only uses rule #{target}, which does not exist, so the test allows every word"
+    ))]
+    UnknownTargetRule { target: usize, line: usize },
+}
+
+impl Warning {
+    /// Get line of scheme file which caused warning
+    pub fn line(&self) -> usize {
+        match self {
+            PhonemeRedefined { line, .. }
+            | ClassRedefined { line, .. }
+            | ClassShadowsLiteral { line, .. }
+            | DuplicateTest { line, .. }
+            | DuplicateRule { line, .. }
+            | ShadowedRule { line, .. }
+            | UnknownReason { line, .. }
+            | UnknownTargetRule { line, .. } => *line,
+        }
+    }
+
+    /// Add offset to every line number of warning
+    pub(crate) fn offset_lines(&mut self, offset: usize) {
+        match self {
+            ClassRedefined {
+                first_line, line, ..
+            }
+            | PhonemeRedefined {
+                first_line, line, ..
+            }
+            | DuplicateTest {
+                first_line, line, ..
+            }
+            | DuplicateRule { first_line, line }
+            | ShadowedRule { first_line, line } => {
+                *first_line += offset;
+                *line += offset;
+            }
+
+            ClassShadowsLiteral {
+                class_line, line, ..
+            } => {
+                *class_line += offset;
+                *line += offset;
+            }
+
+            UnknownReason { line, .. } | UnknownTargetRule { line, .. } => *line += offset,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use fancy_regex::Regex;
+
+use crate::{types::TestDefinition, Phonet};
+
+/// How much a class is used by rules and tests
+///
+/// Create with `Phonet::class_usage()`
+#[derive(Debug, PartialEq, Eq)]
+pub struct ClassUsage {
+    /// Name of class
+    pub name: String,
+    /// Amount of rules which use class, directly or with another class
+    pub rules: usize,
+    /// Amount of segments matched by class, in test words which are matched by rules which use class
+    ///
+    /// Segments are counted once for each rule
+    pub segments: usize,
+}
+
+impl ClassUsage {
+    /// Returns `true` if class never influences the result of any test
+    pub fn is_unused(&self) -> bool {
+        self.rules == 0 || self.segments == 0
+    }
+}
+
+impl Phonet {
+    /// Count rules which use each class, and segments of test words matched by each class
+    ///
+    /// Whole words are searched for segments, as classes may only be used in look-arounds of a rule
+    ///
+    /// Sorted by name of class
+    pub fn class_usage(&self) -> Vec<ClassUsage> {
+        let words: Vec<String> = self
+            .tests
+            .iter()
+            .filter_map(|test| match test {
+                TestDefinition::Test { word, .. } => Some(self.strip_diacritics(word)),
+                TestDefinition::Note(_) => None,
+            })
+            .collect();
+
+        let mut names: Vec<&String> = self.classes.keys().collect();
+        names.sort();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let rules: Vec<_> = self
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.classes.contains(name))
+                    .collect();
+
+                // Invalid rules or classes do not match any segments
+                let segments = match Regex::new(&self.classes[name]) {
+                    Ok(class) => rules
+                        .iter()
+                        .filter_map(|rule| rule.regex().ok())
+                        .map(|regex| {
+                            words
+                                .iter()
+                                .filter(|word| regex.is_match(word).unwrap_or(false))
+                                .map(|word| count_matches(&class, word))
+                                .sum::<usize>()
+                        })
+                        .sum(),
+                    Err(_) => 0,
+                };
+
+                ClassUsage {
+                    name: name.to_string(),
+                    rules: rules.len(),
+                    segments,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Count non-empty matches of regex in text
+fn count_matches(regex: &Regex, text: &str) -> usize {
+    regex
+        .find_iter(text)
+        .filter_map(Result::ok)
+        .filter(|found| !found.as_str().is_empty())
+        .count()
+}
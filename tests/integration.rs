@@ -1,4 +1,4 @@
-use phonet::{self, Phonet};
+use phonet::{self, DisplayLevel, FailReason, MergePolicy, Phonet, ReportFormat};
 
 #[test]
 fn tokipona_should_pass() {
@@ -28,6 +28,15 @@ fn example_should_have_2_fails() {
     );
 }
 
+#[test]
+fn example_scheme_is_example_file() {
+    assert_eq!(
+        phonet::EXAMPLE_SCHEME,
+        include_str!("../examples/example.phonet")
+    );
+    assert_eq!(Phonet::example().run().fail_count, 2);
+}
+
 #[test]
 fn no_tests_to_run() {
     assert_eq!(
@@ -63,3 +72,1401 @@ fn invalid_syntax() {
         "Syntax should not have parsed, but did"
     );
 }
+
+#[test]
+fn display_level_parses_aliases() {
+    assert!(matches!("show-all".parse(), Ok(DisplayLevel::ShowAll)));
+    assert!(matches!("f".parse(), Ok(DisplayLevel::JustFails)));
+    assert!(matches!("dim".parse(), Ok(DisplayLevel::DimPasses)));
+    assert!(matches!("Fails".parse(), Ok(DisplayLevel::JustFails)));
+    assert!(matches!("SUMMARY".parse(), Ok(DisplayLevel::JustSummary)));
+    assert!(matches!("u".parse(), Ok(DisplayLevel::JustSummary)));
+    assert!(" h ".parse::<DisplayLevel>().is_ok());
+    assert!("nothing".parse::<DisplayLevel>().is_err());
+}
+
+#[test]
+fn summary_line_format() {
+    let summary = Phonet::parse("@* Note; + ^[ab]+$; ?+ ab ba c; ?! c")
+        .expect("Failed to parse")
+        .run()
+        .summary_line();
+
+    assert!(
+        summary.starts_with("phonet: 3 passed, 1 failed, 1 notes, "),
+        "Unexpected summary line `{summary}`"
+    );
+    assert!(summary.ends_with("ms"));
+}
+
+#[test]
+fn json_report_is_libtest_stream() {
+    let report = Phonet::parse("@ Bad letter; + ^[ab]+$; ?+ ab c")
+        .expect("Failed to parse")
+        .run()
+        .report(ReportFormat::Json);
+
+    let events: Vec<serde_json::Value> = report
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Line should be valid JSON"))
+        .collect();
+
+    // Suite start, 2 tests (start and end), suite end
+    assert_eq!(events.len(), 6);
+
+    assert_eq!(events[0]["type"], "suite");
+    assert_eq!(events[0]["event"], "started");
+    assert_eq!(events[0]["test_count"], 2);
+
+    assert_eq!(events[2]["name"], "?+ ab");
+    assert_eq!(events[2]["event"], "ok");
+
+    assert_eq!(events[4]["name"], "?+ c");
+    assert_eq!(events[4]["event"], "failed");
+    assert_eq!(events[4]["stdout"], "Bad letter\n");
+
+    assert_eq!(events[5]["event"], "failed");
+    assert_eq!(events[5]["passed"], 1);
+    assert_eq!(events[5]["failed"], 1);
+    assert_eq!(events[5]["stats"]["reasons"][0]["reason"], "Bad letter");
+    assert_eq!(events[5]["stats"]["rules"][0]["rule"], 0);
+}
+
+#[test]
+fn display_level_includes() {
+    let results = Phonet::parse("+ ^a+$; * Note; ?+ a b")
+        .expect("Failed to parse")
+        .run();
+    let [note, pass, fail] = &results.list[..] else {
+        panic!("Expected note and 2 tests");
+    };
+
+    let included = |level: DisplayLevel| {
+        [note, pass, fail]
+            .iter()
+            .map(|item| level.includes(item))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(included(DisplayLevel::ShowAll), [true, true, true]);
+    assert_eq!(included(DisplayLevel::DimPasses), [true, true, true]);
+    assert_eq!(included(DisplayLevel::NotesAndFails), [true, false, true]);
+    assert_eq!(included(DisplayLevel::JustFails), [false, false, true]);
+    assert_eq!(included(DisplayLevel::JustSummary), [false, false, false]);
+}
+
+#[test]
+fn report_sections() {
+    let results = Phonet::parse(
+        "+ ^[ab]+$
+        ?+ a
+        * First
+        ?+ b c
+        * Empty
+        * Second
+        ?! c",
+    )
+    .expect("Failed to parse")
+    .run();
+
+    let sections = results.sections();
+    assert_eq!(sections.len(), 3);
+    assert_eq!(sections[0].name, None);
+    assert_eq!(sections[0].passed, 1);
+    assert_eq!(sections[1].name.as_deref(), Some("First"));
+    assert_eq!((sections[1].passed, sections[1].failed), (1, 1));
+    assert_eq!(sections[2].name.as_deref(), Some("Second"));
+    assert_eq!((sections[2].passed, sections[2].failed), (1, 0));
+
+    let report = results.report(ReportFormat::Json);
+    let summary: serde_json::Value =
+        serde_json::from_str(report.lines().last().unwrap()).expect("Line should be valid JSON");
+    assert_eq!(summary["sections"][1]["name"], "First");
+    assert_eq!(summary["sections"][1]["failed"], 1);
+    assert!(summary["sections"][1]["exec_time"].is_f64());
+
+    // Test suite for each section, with tests nested inside
+    let report = results.report_with(ReportFormat::Junit, Some("sections.phonet"));
+    assert!(report.starts_with("<?xml"));
+    assert_eq!(report.matches("<testsuite ").count(), 3);
+    assert_eq!(report.matches("<testcase ").count(), 4);
+    assert!(report.contains(r#"<testsuite name="sections.phonet" tests="1" failures="0""#));
+    assert!(report.contains(r#"<testsuite name="First" tests="2" failures="1""#));
+    assert_eq!(report.matches("<failure ").count(), 1);
+
+    // Row for each section, followed by its tests
+    let report = results.report(ReportFormat::Csv);
+    let rows: Vec<Vec<&str>> = report
+        .lines()
+        .map(|line| line.split(',').take(6).collect())
+        .collect();
+    assert_eq!(rows.len(), 8);
+    assert_eq!(
+        rows[0],
+        ["type", "section", "name", "passed", "failed", "reason"]
+    );
+    assert_eq!(rows[1], ["section", "", "", "1", "0", ""]);
+    assert_eq!(rows[3], ["section", "First", "", "1", "1", ""]);
+    assert_eq!(rows[4][..5], ["test", "First", "?+ b", "1", "0"]);
+    assert_eq!(rows[5][..5], ["test", "First", "?+ c", "0", "1"]);
+}
+
+#[test]
+fn failure_stats() {
+    let stats =
+        Phonet::parse("! bb; @ Structure; + ^[ab]+$; @ No 'aa'; ! aa; ?+ c d bb aa aaa; ?! ab")
+            .expect("Failed to parse")
+            .run()
+            .stats();
+
+    assert_eq!(
+        stats.reasons,
+        [
+            ("Structure".to_string(), 2),
+            ("No 'aa'".to_string(), 2),
+            ("No reason given".to_string(), 1),
+            ("Valid, but should be invalid".to_string(), 1),
+        ]
+    );
+    assert_eq!(stats.rules, [(1, 2), (2, 2), (0, 1)]);
+}
+
+#[test]
+fn environment_shorthand() {
+    let scheme =
+        Phonet::parse("$N = [mn]; ! <N> / _k; ?+ nt ka; ?! nk ank").expect("Failed to parse");
+
+    assert_eq!(scheme.run().fail_count, 0, "Environment did not match");
+}
+
+#[test]
+fn invalid_class_value_errors_at_definition() {
+    assert!(
+        matches!(
+            Phonet::parse("$C = [ptk\n+ ^a$"),
+            Err(phonet::Error::ClassRegexFail { line: 1, .. })
+        ),
+        "Invalid class value should fail at class definition"
+    );
+}
+
+#[test]
+fn class_warnings() {
+    let scheme =
+        Phonet::parse("$C = [ptk]\n$V = [aeiou]\n$C = [pt]\n+ ^CV$").expect("Failed to parse");
+
+    assert!(
+        matches!(
+            scheme.warnings.as_slice(),
+            [
+                phonet::Warning::ClassRedefined {
+                    first_line: 1,
+                    line: 3,
+                    ..
+                },
+                phonet::Warning::ClassShadowsLiteral {
+                    class_line: 2,
+                    line: 4,
+                    ..
+                },
+                phonet::Warning::ClassShadowsLiteral {
+                    class_line: 3,
+                    line: 4,
+                    ..
+                },
+            ]
+        ),
+        "Unexpected warnings: {:?}",
+        scheme.warnings
+    );
+
+    assert!(
+        matches!(
+            scheme.deny_warnings(),
+            Err(phonet::Error::DeniedWarning { .. })
+        ),
+        "Warnings should have been denied"
+    );
+
+    assert!(Phonet::parse("$C = [ptk]; + ^<C>$")
+        .expect("Failed to parse")
+        .deny_warnings()
+        .is_ok());
+}
+
+#[test]
+fn unicode_escapes() {
+    let scheme = Phonet::parse(r"$V = a\u{0303} | e; + ^<V>+$; ?+ a\u{0303}e e; ?! a")
+        .expect("Failed to parse");
+
+    assert_eq!(scheme.run().fail_count, 0, "Unicode escapes did not match");
+}
+
+#[test]
+fn class_references_with_uppercase_literals() {
+    // Classes are only referenced with angle brackets, so uppercase letters are literal
+    let scheme =
+        Phonet::parse("$Vowel = [aeiou]; $C = [ptk]; + ^(<C>|C)<Vowel>+$; ?+ pa Ca; ?! Xa pC")
+            .expect("Failed to parse");
+
+    assert_eq!(scheme.run().fail_count, 0, "Class references did not match");
+    assert!(scheme.warnings.iter().any(|warning| matches!(
+        warning,
+        phonet::Warning::ClassShadowsLiteral { name, .. } if name == "C"
+    )));
+}
+
+#[test]
+fn failed_test_has_rule() {
+    let results = Phonet::parse("$C = [ptk]; + ^<C>+$; ! pp; ?+ tk pp a")
+        .expect("Failed to parse")
+        .run();
+
+    let rules: Vec<_> = results
+        .list
+        .iter()
+        .map(|result| match result {
+            phonet::TestResult::Test { rule, .. } => *rule,
+            phonet::TestResult::Note(_) => None,
+        })
+        .collect();
+
+    assert_eq!(rules, [None, Some(1), Some(0)]);
+    assert_eq!(results.rules[0].pattern.as_str(), "^(?:[ptk])+$");
+}
+
+#[test]
+fn explain_word() {
+    let scheme =
+        Phonet::parse("@ Structure; + ^[ta]+$; @ Repeated; ! (.)\\1").expect("Failed to parse");
+
+    let explanation = scheme.explain("taa");
+    assert!(!explanation.is_valid());
+    assert_eq!(explanation.traces.len(), 2);
+
+    assert!(explanation.traces[0].pass);
+    assert_eq!(explanation.traces[0].span, Some(0..3));
+
+    let failure = explanation.first_failure().expect("Word should be invalid");
+    assert_eq!(failure.rule, 1);
+    assert_eq!(failure.source, "(.)\\1");
+    assert_eq!(failure.line, 1);
+    assert_eq!(failure.span, Some(1..3));
+    assert_eq!(failure.reason.as_deref(), Some("Repeated"));
+
+    assert!(scheme.explain("ta").is_valid());
+}
+
+#[test]
+fn validate_word() {
+    let scheme = Phonet::parse("@ Structure; + ^[tai]+$; @ Repeated; ! (.)\\1; ! ii")
+        .expect("Failed to parse");
+
+    let analysis = scheme.validate("tiix");
+    assert!(!analysis.is_valid());
+    assert!(matches!(
+        analysis.validity,
+        phonet::ValidStatus::Invalid { rule: 0, .. }
+    ));
+    assert_eq!(
+        analysis
+            .violations
+            .iter()
+            .map(|trace| trace.rule)
+            .collect::<Vec<_>>(),
+        [0, 1, 2]
+    );
+    assert_eq!(analysis.first_violation().map(|trace| trace.rule), Some(0));
+    assert_eq!(analysis.spans(), [1..3, 1..3]);
+
+    let analysis = scheme.validate("tai");
+    assert!(analysis.is_valid());
+    assert!(analysis.validity.is_valid());
+}
+
+#[test]
+fn generation_safeguards() {
+    let scheme = Phonet::parse("$_ = [ab]; @ Never; + ^c$").expect("Failed to parse");
+
+    assert!(matches!(
+        scheme.generate(1, 3..3),
+        Err(phonet::Error::EmptyGenerateLength { min: 3, max: 3 })
+    ));
+
+    match scheme.generate_with_attempts(1, 1..4, 50) {
+        Err(phonet::Error::GenerationFailed {
+            attempts,
+            rejections,
+        }) => {
+            assert_eq!(attempts, 50);
+            assert_eq!(rejections, "rule #0 (Never): 50");
+        }
+        _ => panic!("Generation should fail"),
+    }
+}
+
+#[test]
+fn pass_rate_and_intent_counts() {
+    let results = Phonet::parse("+ ^[ab]+$; ?+ ab ba c; ?! c ab")
+        .expect("Failed to parse")
+        .run();
+
+    assert_eq!(
+        results.positive,
+        phonet::IntentCounts {
+            passed: 2,
+            failed: 1
+        }
+    );
+    assert_eq!(
+        results.negative,
+        phonet::IntentCounts {
+            passed: 1,
+            failed: 1
+        }
+    );
+    assert_eq!(results.pass_rate(), Some(60.0));
+
+    assert_eq!(Phonet::parse("").unwrap().run().pass_rate(), None);
+}
+
+#[test]
+fn tests_have_line() {
+    let results = Phonet::parse("+ ^a+$\n\n?+ a aa\n?! b; ?+ b")
+        .expect("Failed to parse")
+        .run();
+
+    let lines: Vec<_> = results
+        .list
+        .iter()
+        .filter_map(|result| match result {
+            phonet::TestResult::Test { line, .. } => *line,
+            phonet::TestResult::Note(_) => None,
+        })
+        .collect();
+
+    assert_eq!(lines, [3, 3, 4, 4]);
+}
+
+#[test]
+fn labelled_tests() {
+    let scheme = Phonet::parse("+ ^[a-z]+$; ?+ [gen-sg form] tanem tanom; ?! x; ?+ y")
+        .expect("Failed to parse");
+
+    let labels: Vec<_> = scheme
+        .tests
+        .iter()
+        .filter_map(|test| match test {
+            phonet::TestDefinition::Test { word, label, .. } => {
+                Some((word.as_str(), label.clone()))
+            }
+            phonet::TestDefinition::Note(_) => None,
+        })
+        .collect();
+
+    assert_eq!(
+        labels,
+        [
+            ("tanem", Some("gen-sg form".to_string())),
+            ("tanom", Some("gen-sg form".to_string())),
+            ("x", None),
+            ("y", None),
+        ]
+    );
+
+    assert_eq!(
+        scheme.minify(true),
+        ";+^[a-z]+$;?+y;?!x;?+[gen-sg form]tanem tanom"
+    );
+
+    let report = scheme.run().report(ReportFormat::Json);
+    assert!(report.contains(r#""name":"?+ [gen-sg form] tanem""#));
+
+    assert!(matches!(
+        Phonet::parse("?+ [label tanem"),
+        Err(phonet::Error::UnclosedTestLabel { line: 1 })
+    ));
+}
+
+#[test]
+fn lazy_compilation() {
+    // Invalid rule is only found when compiled
+    let options = phonet::ParseOptions {
+        lazy: true,
+        ..Default::default()
+    };
+    let scheme = Phonet::parse_with("+ ^a; + (; ?! b", &options)
+        .expect("Lazy parse should not compile rules");
+    assert!(matches!(
+        scheme.compile_all(),
+        Err(phonet::Error::RegexFail { line: 1, .. })
+    ));
+
+    match Phonet::parse("$C = [ptk]; + ^a; + <C> (") {
+        Err(err @ phonet::Error::RegexFail { .. }) => {
+            assert!(err.to_string().contains("rule `<C> (`"));
+        }
+        _ => panic!("Expected regex error"),
+    }
+
+    // Word is rejected by first rule, before invalid rule is used
+    assert_eq!(scheme.run().fail_count, 0);
+}
+
+#[test]
+fn weighted_generation() {
+    let scheme = Phonet::parse("+ ^([tk][ai])+$; ?+ ta ka kita taka; ?+ xa; ?! ti")
+        .expect("Failed to parse");
+
+    let words = scheme
+        .generate_weighted(20, 2..7)
+        .expect("Failed to generate");
+    assert_eq!(words.len(), 20);
+    for word in words {
+        // Only letters of valid positive tests
+        assert!(word.chars().all(|ch| "tkai".contains(ch)), "{word}");
+        assert!(scheme.explain(&word).is_valid(), "{word}");
+    }
+
+    assert!(matches!(
+        Phonet::parse("+ ^a$; ?! a")
+            .unwrap()
+            .generate_weighted(1, 1..2),
+        Err(phonet::Error::EmptyCorpus)
+    ));
+}
+
+#[test]
+fn jobs_keep_order() {
+    let scheme = Phonet::example();
+
+    // Order and results of tests, as a comparable list
+    let summarize = |results: &phonet::Results| {
+        results
+            .list
+            .iter()
+            .map(|item| match item {
+                phonet::TestResult::Note(note) => format!("* {note}"),
+                phonet::TestResult::Test { word, pass, .. } => format!("{word} {pass}"),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let serial = scheme.run();
+    for jobs in [0, 2, 3, 7, 1000] {
+        let parallel = scheme.run_jobs(jobs);
+
+        assert_eq!(summarize(&parallel), summarize(&serial), "jobs: {jobs}");
+        assert_eq!(parallel.fail_count, serial.fail_count);
+        assert_eq!(parallel.positive, serial.positive);
+        assert_eq!(parallel.negative, serial.negative);
+    }
+}
+
+#[test]
+fn validate_stream() {
+    let scheme = Phonet::parse("@ Structure; + ^[ab]+$").expect("Failed to parse");
+
+    let mut output = Vec::new();
+    let counts = scheme
+        .validate_stream("ab\n\n  ba \nc\n".as_bytes(), &mut output)
+        .expect("Failed to validate");
+
+    assert_eq!(
+        counts,
+        phonet::StreamCounts {
+            valid: 2,
+            invalid: 1
+        }
+    );
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "ab\tvalid\nba\tvalid\nc\tinvalid\tStructure\n"
+    );
+}
+
+#[test]
+fn duplicate_tests() {
+    let mut scheme =
+        Phonet::parse("+ ^[ab]+$\n?+ ab ba\n?! ab\n?+ [again] ab").expect("Failed to parse");
+
+    assert_eq!(
+        scheme
+            .warnings
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>(),
+        ["Test `?+ ab` at line 4 is a duplicate of test at line 2. Use `--dedup` to remove duplicates"]
+    );
+
+    assert_eq!(scheme.dedup_tests(), 1);
+    assert_eq!(scheme.test_count(), 3);
+    assert!(scheme.warnings.is_empty());
+}
+
+#[test]
+fn redundant_rules() {
+    let scheme = Phonet::parse(
+        "$C = [bc]\n! aa\n! baa\n+ ^[abc]+$\n+ ^[abc]+$\n! a<C>\n! cacb\n! ^cc\n! ccc",
+    )
+    .expect("Failed to parse");
+
+    assert_eq!(
+        scheme
+            .warnings
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>(),
+        [
+            "Negative rule at line 3 only matches words which also match negative rule at line 2, so it never changes a result",
+            "Rule at line 5 is the same as rule at line 4, so it never changes a result",
+            "Negative rule at line 7 only matches words which also match negative rule at line 6, so it never changes a result",
+        ]
+    );
+}
+
+#[test]
+fn rule_targeted_tests() {
+    let scheme =
+        Phonet::parse("+ ^[ptk]a$; ?^+ pa ta; ?^! xa\n! aa; ?^+ pa; ?^! baa\n?+ pa; ?! xa")
+            .expect("Failed to parse");
+
+    let results = scheme.run();
+    assert_eq!(results.fail_count, 0);
+
+    // Word is tested with only rule above, ignoring first rule
+    let targets: Vec<_> = results
+        .list
+        .iter()
+        .filter_map(|result| match result {
+            phonet::TestResult::Test { target, .. } => Some(*target),
+            phonet::TestResult::Note(_) => None,
+        })
+        .collect();
+    assert_eq!(
+        targets,
+        [Some(0), Some(0), Some(0), Some(1), Some(1), None, None]
+    );
+
+    // Targeted tests stay after their rule
+    assert_eq!(
+        scheme.minify(true),
+        ";+^[ptk]a$;?^+pa;?^+ta;?^!xa;!aa;?^+pa;?^!baa;?+pa;?!xa"
+    );
+    assert!(!scheme.minify(false).contains('?'));
+
+    // Duplicate targeted tests are removed from minified scheme
+    let mut scheme =
+        Phonet::parse("+ ^[ptk]a$; ?^+ pa ta; ?^+ pa\n! aa; ?^+ pa").expect("Failed to parse");
+    assert_eq!(scheme.dedup_tests(), 1);
+    assert_eq!(
+        scheme.minify(true),
+        ";+^[ptk]a$;?^+pa;?^+ta;!aa;?^+pa;?+;?!"
+    );
+
+    assert!(matches!(
+        Phonet::parse("?^+ a"),
+        Err(phonet::Error::NoTargetRule { line: 1 })
+    ));
+}
+
+#[test]
+fn position_qualifiers() {
+    let scheme = Phonet::parse(
+        "$V = [aeiou]
+        ! initial: ng
+        ! final: h
+        ! medial: x
+        ! onset: st
+        ! coda: rt
+        ?+ angi xa ax hat asat arta
+        ?! nga ah axa sta asta art",
+    )
+    .expect("Failed to parse");
+
+    assert_eq!(scheme.run().fail_count, 0);
+
+    // Vowel class is not defined
+    assert!(matches!(
+        Phonet::parse("! initial: ng\n! coda: rt"),
+        Err(phonet::Error::QualifierWithoutVowels { qualifier, line: 2 }) if qualifier == "coda"
+    ));
+}
+
+#[test]
+fn inline_reasons_and_word_boundaries() {
+    let results = Phonet::parse(
+        "$N = [mn]
+        @ Structure
+        + ^[mnka]+$
+        ! <N> / _k @ No nasal before 'k'
+        ! k / _# @ No final 'k'
+        ! aa
+        ?+ mnk ak aa",
+    )
+    .expect("Failed to parse")
+    .run();
+
+    let reasons: Vec<_> = results
+        .list
+        .iter()
+        .filter_map(|result| match result {
+            phonet::TestResult::Test { reason, .. } => Some(reason.text()),
+            phonet::TestResult::Note(_) => None,
+        })
+        .collect();
+
+    // Rule after inline reason uses reason from before
+    assert_eq!(
+        reasons,
+        ["No nasal before 'k'", "No final 'k'", "Structure"]
+    );
+}
+
+#[test]
+fn strip_diacritics() {
+    let scheme = Phonet::parse(
+        "% strip ˈ \\u{0301}
+        + ^[tasoː]+$
+        ?+ ˈtaso ta\\u{0301}soˈ taːso
+        ?! ˈtaxo",
+    )
+    .expect("Failed to parse");
+
+    assert_eq!(scheme.strip_diacritics("ˈta\u{0301}so"), "taso");
+    assert_eq!(scheme.run().fail_count, 0);
+    assert!(scheme.minify(false).starts_with("%strip ˈ\u{0301};"));
+
+    // Strip all, except preserved characters
+    let scheme = Phonet::parse(
+        "% strip
+        % preserve ː
+        + ^[taso]+$
+        ?+ ta\\u{0301}˥so ˈtaso
+        ?! taːso",
+    )
+    .expect("Failed to parse");
+
+    assert_eq!(scheme.run().fail_count, 0);
+
+    assert!(matches!(
+        Phonet::parse("% keep ˈ"),
+        Err(phonet::Error::UnknownDirective { line: 1, .. })
+    ));
+}
+
+#[test]
+fn feature_classes() {
+    let scheme = Phonet::parse(
+        "$D = [+voice, +stop]
+        $L = [+labial]
+        /p/ = [-voice, +labial, +stop]
+        /b/ = [+voice, +labial, +stop]
+        /d/ = [+voice, +stop]
+        /ts/ = [-voice, +stop]
+        /m/ = [+voice, +labial, +nasal]
+        /a/ = [+vowel]
+        + ^(<D>|<L>|ts|a)+$
+        ! <D>$
+        ?+ bada mapa tsa
+        ?! tad ka",
+    )
+    .expect("Failed to parse");
+
+    assert_eq!(scheme.classes.get("D").unwrap(), "(?:[bd])");
+    assert_eq!(scheme.classes.get("L").unwrap(), "(?:[pbm])");
+    assert_eq!(scheme.phonemes.len(), 6);
+    assert_eq!(scheme.run().fail_count, 0);
+
+    // Without phonemes, features are a character set
+    let scheme = Phonet::parse("$D = [+voice]").expect("Failed to parse");
+    assert_eq!(scheme.classes.get("D").unwrap(), "(?:[+voice])");
+
+    assert!(matches!(
+        Phonet::parse("/p/ = [+stop]; $D = [+voice]"),
+        Err(phonet::Error::UnknownFeature { line: 1, .. })
+    ));
+    assert!(matches!(
+        Phonet::parse("/p/ [+stop]"),
+        Err(phonet::Error::InvalidPhoneme { line: 1 })
+    ));
+}
+
+#[test]
+fn accidental_gaps() {
+    let scheme = Phonet::parse(
+        "$C = [ptk]
+        $V = [ai]
+        + ^(<C><V>)+$
+        ?+ pa ti ka",
+    )
+    .expect("Failed to parse");
+
+    let gaps: Vec<_> = scheme
+        .gaps(&scheme.corpus(), 2)
+        .into_iter()
+        .map(|gap| gap.sequence)
+        .collect();
+
+    // Vowel and consonant pairs are not possible
+    assert_eq!(gaps, ["ki", "pi", "ta"]);
+
+    // Sequences across syllables are possible
+    let gaps = scheme.gaps(&["tipa".to_string()], 2);
+    let sequences: Vec<_> = gaps.iter().map(|gap| gap.sequence.as_str()).collect();
+    assert_eq!(sequences, ["ap", "at", "it", "pi", "ta"]);
+    assert_eq!(gaps[0].example, "tapa");
+}
+
+#[test]
+fn quickfix_report() {
+    let results = Phonet::parse(
+        "+ ^[ao]+$
+        ?+ ao
+        ?+ ax",
+    )
+    .expect("Failed to parse")
+    .run();
+
+    assert_eq!(
+        results.report_with(ReportFormat::Quickfix, Some("lang.phonet")),
+        "lang.phonet:3:1: error: ?+ ax: No reason given\n"
+    );
+
+    let err = Phonet::parse("+ a\n+ (").unwrap_err();
+    assert_eq!(err.line(), Some(2));
+    assert!(err
+        .quickfix("lang.phonet")
+        .starts_with("lang.phonet:2:1: error: "));
+}
+
+#[test]
+fn compare_schemes() {
+    let old = Phonet::parse(
+        "$V = [ao]
+        + ^[tk<V>]+$
+        ! <V><V>
+        ?+ taka
+        ?! tao",
+    )
+    .expect("Failed to parse");
+
+    // Refactored, but allows 'aa'
+    let new = Phonet::parse(
+        "$V = [ao]
+        + ^[tk<V>]+$
+        ! ao|oa
+        ?+ taka
+        ?! taa",
+    )
+    .expect("Failed to parse");
+
+    assert_eq!(old.test_words(), ["taka", "tao"]);
+
+    let differences = old.compare(&new, ["taka", "taa", "too", "tao"]);
+    assert_eq!(
+        differences,
+        [
+            phonet::Difference {
+                word: "taa".to_string(),
+                first: false,
+                second: true,
+            },
+            phonet::Difference {
+                word: "too".to_string(),
+                first: false,
+                second: true,
+            },
+        ]
+    );
+}
+
+#[test]
+fn class_usage() {
+    let scheme = Phonet::parse(
+        "$C = [ptk]
+        $V = [ao]
+        $S = <C><V>
+        $N = [mn]
+        $X = [x]
+        + ^<S>+$
+        ! <X>
+        ?+ taka pa
+        ?! tka",
+    )
+    .expect("Failed to parse");
+
+    let usage: Vec<_> = scheme
+        .class_usage()
+        .into_iter()
+        .map(|class| (class.name.clone(), class.rules, class.is_unused()))
+        .collect();
+
+    assert_eq!(
+        usage,
+        [
+            ("C".to_string(), 1, false),
+            ("N".to_string(), 0, true),
+            ("S".to_string(), 1, false),
+            ("V".to_string(), 1, false),
+            // Used by rule, but never matches a test
+            ("X".to_string(), 1, true),
+        ]
+    );
+
+    // Segments of 'taka' and 'pa'
+    assert_eq!(scheme.class_usage()[0].segments, 3);
+}
+
+#[test]
+fn structured_reasons() {
+    let results = Phonet::parse(
+        "+ ^[taxo]+$
+        ! x @ No 'x'
+        ! oo
+        ?+ taxo tao tamo taoo",
+    )
+    .expect("Failed to parse")
+    .run();
+
+    let reasons: Vec<_> = results
+        .list
+        .into_iter()
+        .filter_map(|result| match result {
+            phonet::TestResult::Test { reason, .. } => Some(reason),
+            phonet::TestResult::Note(_) => None,
+        })
+        .collect();
+
+    assert_eq!(
+        reasons,
+        [
+            FailReason::Custom {
+                reason: "No 'x'".to_string(),
+                key: 0,
+                rule: 1,
+                span: Some(2..3),
+            },
+            FailReason::Passed,
+            FailReason::NoReasonGiven {
+                rule: 0,
+                span: None,
+            },
+            // Inline reason is only used for its rule
+            FailReason::NoReasonGiven {
+                rule: 2,
+                span: Some(2..4),
+            },
+        ]
+    );
+    assert_eq!(reasons[0].rule(), Some(1));
+    assert_eq!(reasons[3].span(), Some(2..4));
+}
+
+#[test]
+fn merge_schemes() {
+    let base = || {
+        Phonet::parse(
+            "$V = [ao]
+            @ Only 't', 'k', and vowels
+            + ^[tk<V>]+$
+            ?+ taka",
+        )
+        .expect("Failed to parse")
+    };
+    let extension = || {
+        Phonet::parse(
+            "$V = [aoe]
+            @ No double vowels
+            ! <V><V>
+            ?^+ taka
+            ?! tao tae",
+        )
+        .expect("Failed to parse")
+    };
+
+    let merged = base()
+        .merge(extension(), MergePolicy::PreferFirst)
+        .expect("Failed to merge");
+
+    assert_eq!(merged.rules.len(), 2);
+    assert_eq!(merged.classes.get("V").unwrap(), "(?:[ao])");
+    assert_eq!(
+        merged.reasons[merged.rules[1].reason_ref.unwrap()],
+        "No double vowels"
+    );
+    assert!(matches!(
+        &merged.tests[1],
+        phonet::TestDefinition::Test {
+            target: Some(1),
+            ..
+        }
+    ));
+
+    // 'tae' is invalid with rule of base
+    let results = merged.run();
+    assert_eq!(results.fail_count, 0);
+
+    let merged = base()
+        .merge(extension(), MergePolicy::PreferSecond)
+        .expect("Failed to merge");
+    assert_eq!(merged.classes.get("V").unwrap(), "(?:[aoe])");
+    // Rule of base uses class of extension
+    assert!(merged.rules[0].pattern.contains("[aoe]"));
+    assert_eq!(merged.run().fail_count, 0);
+    // Lines of extension are after lines of base
+    assert_eq!(merged.rules[1].line, 7);
+    assert!(matches!(
+        &merged.tests[1],
+        phonet::TestDefinition::Test { line: Some(8), .. }
+    ));
+
+    assert!(matches!(
+        base().merge(extension(), MergePolicy::Deny),
+        Err(phonet::Error::MergeConflict { .. })
+    ));
+}
+
+#[test]
+fn backtrack_limit() {
+    let options = phonet::ParseOptions {
+        backtrack_limit: Some(100),
+        ..Default::default()
+    };
+
+    // Catastrophic backtracking, with back-reference
+    let results = Phonet::parse_with(
+        "+ ^a
+        ! ^(a+)+\\1b$
+        ?! aaaaaaaaaaaaaaaaaaaaaaaa
+        ?+ a",
+        &options,
+    )
+    .expect("Failed to parse")
+    .run();
+
+    assert_eq!(results.fail_count, 1);
+    assert!(matches!(
+        &results.list[0],
+        phonet::TestResult::Test {
+            pass: false,
+            reason: FailReason::Timeout { rule: 1, line: 2 },
+            ..
+        }
+    ));
+}
+
+#[test]
+fn match_error() {
+    let options = phonet::ParseOptions {
+        lazy: true,
+        ..Default::default()
+    };
+
+    // Invalid rule is only compiled when tests are run
+    let results = Phonet::parse_with(
+        "+ ^a
+        ! \\p{NotAClass}
+        ?+ a
+        ?! b",
+        &options,
+    )
+    .expect("Failed to parse")
+    .run();
+
+    assert_eq!(results.fail_count, 1);
+    assert!(matches!(
+        &results.list[0],
+        phonet::TestResult::Test {
+            pass: false,
+            reason: FailReason::MatchError {
+                rule: 1,
+                line: 2,
+                ..
+            },
+            ..
+        }
+    ));
+    // Other tests still run
+    assert!(matches!(
+        &results.list[1],
+        phonet::TestResult::Test { pass: true, .. }
+    ));
+}
+
+#[test]
+fn optional_classes() {
+    let scheme = Phonet::parse(
+        "$C = [ptk]; $V = [aeiou]; $N = n|ng
+        + ^(<C?><V><N?>)+$
+        ! <N>{2,}
+        ! (<V>)\\1",
+    )
+    .expect("Failed to parse");
+
+    for word in ["a", "pa", "an", "pang", "apatang", "pai"] {
+        assert!(scheme.explain(word).is_valid(), "{word}");
+    }
+    for word in ["p", "pt", "anng", "angn", "paa"] {
+        assert!(!scheme.explain(word).is_valid(), "{word}");
+    }
+
+    assert!(matches!(
+        Phonet::parse("$V = [aeiou]; + <V>{,3}"),
+        Err(phonet::Error::InvalidRepetition { line: 1, .. })
+    ));
+    assert!(matches!(
+        Phonet::parse("$V = [aeiou]?; + <V>+"),
+        Err(phonet::Error::EmptyQuantifiedClass { line: 1, .. })
+    ));
+}
+
+#[test]
+fn cache_repeated_words() {
+    // Repeated word, with and without target rule
+    let scheme = Phonet::parse("+ ^[ab]+$; ! bb; ?^! abb; ?! abb; ?+ abb; ?+ ab ab")
+        .expect("Failed to parse");
+
+    let outcomes = |results: phonet::Results| {
+        results
+            .list
+            .iter()
+            .map(|item| match item {
+                phonet::TestResult::Test { pass, rule, .. } => (*pass, *rule),
+                phonet::TestResult::Note(_) => panic!("Expected test"),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let expected = vec![
+        (true, Some(1)),
+        (true, Some(1)),
+        (false, Some(1)),
+        (true, None),
+        (true, None),
+    ];
+    assert_eq!(outcomes(scheme.run()), expected);
+    assert_eq!(
+        outcomes(scheme.run_with(&phonet::RunOptions {
+            jobs: 2,
+            no_cache: true,
+        })),
+        expected
+    );
+}
+
+#[test]
+fn translate_reasons() {
+    let mut scheme =
+        Phonet::parse("@* No 'x'; ! x; @ No 'y'; ! y; ?+ x y").expect("Failed to parse");
+
+    let translations = [("No 'x'".to_string(), "Sin 'x'".to_string())]
+        .into_iter()
+        .collect();
+    let missing = scheme.translate(&translations);
+    assert_eq!(missing, ["No 'y'"]);
+
+    let results = scheme.run();
+    assert!(matches!(&results.list[0], phonet::TestResult::Note(note) if note.text == "Sin 'x'"));
+    assert!(matches!(
+        &results.list[1],
+        phonet::TestResult::Test { reason, .. } if reason.text() == "Sin 'x'"
+    ));
+    assert!(matches!(
+        &results.list[2],
+        phonet::TestResult::Test { reason, .. } if reason.text() == "No 'y'"
+    ));
+}
+
+#[test]
+fn self_test() {
+    use phonet::CheckStatus;
+
+    assert!(Phonet::example().self_test().passed());
+
+    let options = phonet::ParseOptions {
+        lazy: true,
+        ..Default::default()
+    };
+    let scheme =
+        Phonet::parse_with("@ Unused; @ Used; + ^a+$; + (", &options).expect("Failed to parse");
+    let self_test = scheme.self_test();
+    assert!(!self_test.passed());
+
+    let statuses: Vec<_> = self_test.checks.iter().map(|check| &check.status).collect();
+    assert_eq!(statuses[0], &CheckStatus::Passed);
+    assert!(matches!(statuses[1], CheckStatus::Failed(problems) if problems.len() == 1));
+    assert!(matches!(statuses[2], CheckStatus::Failed(problems) if problems[0].contains("Unused")));
+    assert!(matches!(statuses[3], CheckStatus::Skipped(_)));
+}
+
+#[test]
+fn run_warnings() {
+    let mut scheme = Phonet::parse("@ No 'x'; ! x; + ^a; ?^+ ab; ?+ x").expect("Failed to parse");
+    assert!(scheme.run().warnings.is_empty());
+
+    // Remove reason and target rule, after parsing
+    scheme.reasons.clear();
+    scheme.rules.truncate(1);
+
+    let results = scheme.run();
+    assert!(matches!(
+        results.warnings[..],
+        [
+            phonet::Warning::UnknownReason { reason: 0, line: 1 },
+            phonet::Warning::UnknownTargetRule { target: 1, line: 1 },
+        ]
+    ));
+    assert!(matches!(
+        &results.list[1],
+        phonet::TestResult::Test {
+            reason: FailReason::NoReasonGiven { .. },
+            ..
+        }
+    ));
+}
+
+#[test]
+fn parse_with_limits() {
+    use phonet::{Error, Limits};
+
+    // Classes which use each other in a loop
+    assert!(matches!(
+        Phonet::parse("$A = <B>; $B = <A>; + <A>"),
+        Err(Error::ClassTooDeep { line: 1, .. })
+    ));
+
+    let limits = Limits {
+        max_len: 1000,
+        max_statement_len: 20,
+        max_class_depth: 2,
+        max_pattern_len: 50,
+    };
+    assert!(Phonet::parse_with_limits("$V = [aeiou]; + ^<V>+$; ?+ a", limits.clone()).is_ok());
+
+    assert!(matches!(
+        Phonet::parse_with_limits(&"?+ a\n".repeat(500), limits.clone()),
+        Err(Error::SchemeTooLong {
+            len: 2500,
+            max: 1000
+        })
+    ));
+    assert!(matches!(
+        Phonet::parse_with_limits(&format!("\n?+ {}", "a".repeat(100)), limits.clone()),
+        Err(Error::StatementTooLong { line: 2, .. })
+    ));
+    assert!(matches!(
+        Phonet::parse_with_limits("$A = a; $B = <A>; $C = <B>; $D = <C>", limits.clone()),
+        Err(Error::ClassTooDeep { .. })
+    ));
+    assert!(matches!(
+        Phonet::parse_with_limits("$A = abcdefghij; $B = <A><A><A>; + <B><B>", limits),
+        Err(Error::PatternTooLong { .. })
+    ));
+}
+
+#[test]
+fn slowest_tests() {
+    let results = Phonet::parse("+ ^[a-z]+$; ?+ a b c; * Note; ?! D")
+        .expect("Failed to parse")
+        .run();
+
+    let slowest = results.slowest(3);
+    assert_eq!(slowest.len(), 3);
+    assert!(slowest
+        .iter()
+        .all(|item| matches!(item, phonet::TestResult::Test { .. })));
+
+    // Sorted slowest first
+    let durations: Vec<_> = slowest
+        .iter()
+        .map(|item| match item {
+            phonet::TestResult::Test { duration, .. } => *duration,
+            phonet::TestResult::Note(_) => unreachable!(),
+        })
+        .collect();
+    assert!(durations.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    assert_eq!(results.slowest(10).len(), 4);
+    assert!(results.slowest(0).is_empty());
+}
+
+#[test]
+fn note_levels() {
+    use phonet::{NoteFilter, NoteLevel, TestResult};
+
+    let results = Phonet::parse("* == Header ==; * Some note; *! Important; @* Reason note")
+        .expect("Failed to parse")
+        .run();
+
+    let notes: Vec<_> = results
+        .list
+        .iter()
+        .filter_map(|item| match item {
+            TestResult::Note(note) => Some(note),
+            TestResult::Test { .. } => None,
+        })
+        .collect();
+
+    let levels: Vec<_> = notes.iter().map(|note| note.level).collect();
+    assert_eq!(
+        levels,
+        [
+            NoteLevel::Heading,
+            NoteLevel::Info,
+            NoteLevel::Warning,
+            NoteLevel::Info
+        ]
+    );
+    assert_eq!(notes[0].text, "== Header ==");
+    assert_eq!(notes[2].text, "Important");
+
+    let included = |filter: NoteFilter| -> Vec<bool> {
+        notes.iter().map(|note| filter.includes(note)).collect()
+    };
+    assert_eq!(included(NoteFilter::All), [true, true, true, true]);
+    assert_eq!(included(NoteFilter::Headings), [true, false, false, false]);
+    assert_eq!(included(NoteFilter::Off), [false, false, false, false]);
+}
+
+#[test]
+fn canonical_report() {
+    let results = Phonet::parse(
+        "@ No 'x'; ! x; + ^[a-z]+$
+        * == Words ==
+        ?+ a x
+        ?+ [label] b
+        *! Important
+        ?! c",
+    )
+    .expect("Failed to parse")
+    .run();
+
+    assert_eq!(
+        results.report(ReportFormat::Canonical),
+        "* == Words ==
+pass ?+ a
+FAIL ?+ x  @ No 'x'
+pass ?+ [label] b
+*! Important
+FAIL ?! c  @ Valid, but should be invalid
+3 passed, 2 failed
+"
+    );
+}
+
+#[test]
+fn define_and_extra_rules() {
+    let file = "$V = [aeiou]; @ Structure; + ^[tk<V>]+$; ?+ taky tak; ?! taak";
+    let results = Phonet::parse(file).expect("Failed to parse").run();
+    assert_eq!(results.fail_count, 2);
+
+    let options = phonet::ParseOptions {
+        defines: vec!["V = [aeiouy]".to_string()],
+        extra_rules: vec!["! <V><V>".to_string()],
+        ..Default::default()
+    };
+    let scheme = Phonet::parse_with(file, &options).expect("Failed to parse");
+    // Overriding class is not a warning
+    assert!(scheme.warnings.is_empty());
+    let results = scheme.run();
+    assert_eq!(results.fail_count, 0);
+    // Reason of file is not used by extra rule
+    assert_eq!(results.rules.len(), 2);
+    assert_eq!(results.rules[1].reason_ref, None);
+
+    let options = phonet::ParseOptions {
+        extra_rules: vec!["?+ tak".to_string()],
+        ..Default::default()
+    };
+    assert!(matches!(
+        Phonet::parse_with(file, &options),
+        Err(phonet::Error::InvalidExtraRule { .. })
+    ));
+}
+
+#[test]
+fn align_word() {
+    let scheme = Phonet::parse(
+        "$C = [ptk]; $V = [aeiouy]
+        + ^(<C><V>)+$
+        + ^<C>
+        ! xx
+        ?! taky",
+    )
+    .expect("Failed to parse");
+
+    let alignment = scheme.align("taky");
+    assert_eq!(alignment.rules.len(), 2);
+
+    // Only last repetition of group is captured
+    let rule = &alignment.rules[0];
+    assert_eq!((rule.rule, rule.span.clone()), (0, 0..4));
+    assert_eq!(
+        rule.segments,
+        [
+            phonet::Segment {
+                class: "C".to_string(),
+                span: 2..3,
+            },
+            phonet::Segment {
+                class: "V".to_string(),
+                span: 3..4,
+            },
+        ]
+    );
+
+    let rule = &alignment.rules[1];
+    assert_eq!(rule.rule, 1);
+    assert_eq!(rule.segments.len(), 1);
+    assert_eq!(rule.segments[0].span, 0..1);
+
+    // Positive rules which do not match are not included
+    assert!(scheme.align("xyz").rules.is_empty());
+}
+
+#[test]
+fn custom_validators() {
+    let file = "@ Vowels must all be the same; + %harmony; ?+ tata; ?! tati; ?+ tito";
+    let mut scheme = Phonet::parse(file).expect("Failed to parse");
+
+    // Every test fails, before validator is registered
+    let results = scheme.run();
+    assert_eq!(results.fail_count, 3);
+    assert!(matches!(
+        &results.list[0],
+        phonet::TestResult::Test {
+            reason: FailReason::MatchError { rule: 0, .. },
+            ..
+        }
+    ));
+
+    let count = scheme.register_validator("harmony", |word| {
+        let mut vowels = word.chars().filter(|letter| "aeiou".contains(*letter));
+        match vowels.next() {
+            Some(first) => vowels.all(|letter| letter == first),
+            None => true,
+        }
+    });
+    assert_eq!(count, 1);
+    assert_eq!(scheme.register_validator("unknown", |_| true), 0);
+    assert!(scheme.warnings.is_empty());
+
+    let results = scheme.run();
+    assert_eq!(results.fail_count, 1);
+    assert!(matches!(
+        &results.list[2],
+        phonet::TestResult::Test {
+            pass: false,
+            reason: FailReason::Custom {
+                rule: 0,
+                span: None,
+                ..
+            },
+            ..
+        }
+    ));
+
+    // Name of validator is not checked as literal text
+    let scheme = Phonet::parse("! o\n! %harmony\n! %xy\n! a%xyb").expect("Failed to parse");
+    assert!(scheme.warnings.is_empty());
+}